@@ -4,10 +4,13 @@
 use std::fs::File;
 use std::io::Read;
 use std::io;
-
+use crate::ans::ans_atom::xyz_frame;
 use crate::ans::minio_input;
 use crate::ans::box_config::{BoxConfig, config_simulation_box};
 use crate::ans::voronoy::do_analysis_wrapper;
+use crate::cli;
+use crate::conv::binary_parser;
+use crate::xyz::particle::ParticleLine;
 use crate::xyz::xyz_reader::Reader;
 
 
@@ -30,7 +33,7 @@ pub fn analysis_wrapper(xyzfile: &str, output: &str, input_from_minio: bool, box
     }
 }
 
-// voronoy analysis method for BCC lattice and cube lattice.
+// voronoy analysis method for BCC or FCC lattice.
 // todo: on data read error
 pub fn read_atoms_and_analysis<R: ?Sized>(input: &mut R, output: &str, on_data_loaded: impl Fn(usize),
                                           box_config: &mut BoxConfig, verbose: bool)
@@ -38,21 +41,55 @@ pub fn read_atoms_and_analysis<R: ?Sized>(input: &mut R, output: &str, on_data_l
 {
     let mut reader = Reader::new(input);
     // todo read atom one by one and compute its index lattice.
-    let snapshot_result = reader.read_snapshot();
+    // columns of extxyz files are mapped by `Properties=` in comment line.
+    let snapshot_result = reader.read_snapshot::<ParticleLine>()
+        .map_err(|e| format!("{:?}", e))
+        .and_then(|snapshot| xyz_frame(&snapshot));
 
     match snapshot_result {
         Err(e) => {
-            println!("read input xyz file error: {:?}", e);
+            println!("read input xyz file error: {}", e);
         }
-        Ok(snapshot) => {
-            let atoms_size = snapshot.size();
+        Ok((atoms, sim_box)) => {
+            let atoms_size = atoms.len();
             on_data_loaded(atoms_size);
-            if config_simulation_box(&snapshot, box_config, verbose) {
+            if config_simulation_box(&atoms, sim_box.as_ref(), box_config, verbose) {
                 // do analysis
-                do_analysis_wrapper(output, box_config.box_size_, box_config.box_start, &snapshot);
+                do_analysis_wrapper(output, box_config, &atoms);
             } else {
                 println!("config simulation box failed");
             }
         }
     }
 }
+
+// voronoy analysis for the first frame of a binary file (v1 or v2 format).
+// Atoms are analysed without converting to text, and the atom ids are kept in the defect output.
+pub fn binary_analysis_wrapper(input: &str, output: &str, bin_standard: cli::FormatStandard, ranks: u32,
+                               box_config: &mut BoxConfig, verbose: bool) {
+    let mut reader = match binary_parser::make_frame_reader(bin_standard, input, ranks) {
+        Ok(reader) => reader,
+        Err(e) => {
+            println!("read input binary file {} error: {}", input, e);
+            return;
+        }
+    };
+    let atoms = match reader.next() {
+        Some(atoms) => atoms,
+        None => {
+            println!("no frame in input binary file {}", input);
+            return;
+        }
+    };
+    if reader.frames() > 1 {
+        println!("Warning: only the first frame is analysed, use `--time-series` to analyse all frames.");
+    }
+    if verbose {
+        println!("atom size is {}", atoms.len());
+    }
+    if config_simulation_box(&atoms, None, box_config, verbose) {
+        do_analysis_wrapper(output, box_config, &atoms);
+    } else {
+        println!("config simulation box failed");
+    }
+}
@@ -0,0 +1,72 @@
+// Atoms that can be analysed by `ans`: atoms in xyz text files or in binary files.
+
+use xyzio::Atom;
+
+use crate::ans::voronoy::Float;
+use crate::conv::binary_types::TypeAtom;
+use crate::xyz::particle::{parse_xyz_atoms, Particle, ParticleLine};
+use crate::xyz::sim_box::SimBox;
+use crate::xyz::xyz_reader::Snapshot;
+
+pub trait AnsAtom: Sync {
+    fn position(&self) -> (Float, Float, Float);
+    fn element(&self) -> &str;
+    // atom id, `None` if the input file does not save atom ids (e.g. xyz files).
+    fn id(&self) -> Option<u64>;
+}
+
+impl AnsAtom for Atom {
+    fn position(&self) -> (Float, Float, Float) {
+        (self.x, self.y, self.z)
+    }
+
+    fn element(&self) -> &str {
+        &self.element
+    }
+
+    fn id(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl AnsAtom for TypeAtom {
+    fn position(&self) -> (Float, Float, Float) {
+        (self.atom_location[0] as Float, self.atom_location[1] as Float, self.atom_location[2] as Float)
+    }
+
+    fn element(&self) -> &str {
+        self.get_name_by_ele_name()
+    }
+
+    fn id(&self) -> Option<u64> {
+        Some(self.id)
+    }
+}
+
+// atom in xyz or extxyz file, the id is only available if it is saved in the file (`id` property of extxyz file).
+pub struct XyzAtom {
+    particle: Particle,
+    has_id: bool,
+}
+
+impl AnsAtom for XyzAtom {
+    fn position(&self) -> (Float, Float, Float) {
+        let (x, y, z) = self.particle.pos;
+        (x as Float, y as Float, z as Float)
+    }
+
+    fn element(&self) -> &str {
+        &self.particle.tp
+    }
+
+    fn id(&self) -> Option<u64> {
+        if self.has_id { Some(self.particle.id as u64) } else { None }
+    }
+}
+
+// atoms and the simulation box (`Lattice=` of extxyz file) of a frame in xyz file.
+pub fn xyz_frame(snapshot: &Snapshot<ParticleLine>) -> Result<(Vec<XyzAtom>, Option<SimBox>), String> {
+    let (particles, has_id) = parse_xyz_atoms(&snapshot.comment, &snapshot.atoms)?;
+    let atoms = particles.into_iter().map(|particle| XyzAtom { particle, has_id }).collect();
+    Ok((atoms, SimBox::from_extxyz_comment(&snapshot.comment)))
+}
@@ -1,37 +1,89 @@
+use crate::ans::ans_atom::AnsAtom;
+use crate::ans::lattice::LatticeType;
 use crate::ans::voronoy;
-use crate::xyz::xyz_reader;
-use xyzio::Atom;
+use crate::xyz::sim_box::SimBox;
 
 /**
  * Created by genshen at 2020/12/25
  */
 
 pub struct BoxConfig {
+    pub lattice: LatticeType,
     pub input_box_start: Vec<voronoy::Float>,
     pub input_box_size: Vec<u64>,
     // from input, length can be 0
+    pub input_lattice_const: Option<voronoy::Float>,
+    // estimate lattice constant from atoms, if it is not specified by user.
+    pub auto_lattice_const: bool,
+    pub lattice_const: voronoy::Float, // lattice constant after determination
     pub box_size_: (usize, usize, usize), // box size after determination
     pub box_start: (voronoy::Float, voronoy::Float, voronoy::Float), // start position of box
+    pub periodic: [bool; 3], // periodic (true) or free (false) boundary of each dimension
+    // cluster defects by 1NN, 2NN or 3NN lattice adjacency, `None` for no clustering.
+    pub cluster_shell: Option<usize>,
+}
+
+#[cfg(test)]
+impl BoxConfig {
+    // box config with determined lattice constant and box size, periodic in all dimensions, for tests.
+    pub fn for_test(lattice: LatticeType, box_size: (usize, usize, usize), lattice_const: voronoy::Float) -> BoxConfig {
+        BoxConfig {
+            lattice,
+            input_box_start: vec![],
+            input_box_size: vec![],
+            input_lattice_const: None,
+            auto_lattice_const: false,
+            lattice_const,
+            box_size_: box_size,
+            box_start: (0.0, 0.0, 0.0),
+            periodic: [true; 3],
+            cluster_shell: None,
+        }
+    }
 }
 
 // set simulation box config and return status: ture for setting ok, false for not ok.
-pub fn config_simulation_box(snapshot: &xyz_reader::Snapshot<Atom>, box_config: &mut BoxConfig, verbose: bool) -> bool {
-    let atoms_size = snapshot.size();
-    if atoms_size % 2 != 0 { // due to feature of BCC lattice
+// `sim_box` is the simulation box given in the input file (e.g. `Lattice=` of extxyz file), if any.
+pub fn config_simulation_box<A: AnsAtom>(atoms: &[A], sim_box: Option<&SimBox>, box_config: &mut BoxConfig, verbose: bool) -> bool {
+    let atoms_size = atoms.len();
+    let atoms_per_cell = box_config.lattice.atoms_per_cell();
+    if atoms_size % atoms_per_cell != 0 { // due to feature of BCC (2 atoms per cell) or FCC (4 atoms per cell) lattice
         println!("bad atoms size");
         return false;
     }
 
+    // determine lattice constant
+    box_config.lattice_const = match box_config.input_lattice_const {
+        Some(lattice_const) => lattice_const,
+        None if box_config.auto_lattice_const => match estimate_lattice_const(atoms, sim_box, box_config) {
+            Some(lattice_const) => lattice_const,
+            None => {
+                println!("can not estimate lattice constant from atoms");
+                return false;
+            }
+        },
+        None => box_config.lattice.default_lattice_const(),
+    };
+    if verbose || box_config.auto_lattice_const {
+        println!("lattice constant: {}", box_config.lattice_const);
+    }
+
     // determine box size
     if box_config.input_box_size.len() == 0 {
         // automatically determine calculate box size via atoms position
-        let size_dim = cube_root(atoms_size / 2);
+        let size_dim = cube_root(atoms_size / atoms_per_cell);
         if size_dim != 0 { // it is a cube box
             box_config.box_size_ = (size_dim, size_dim, size_dim);
         } else {
-            box_config.box_size_ = auto_get_box_size(&snapshot.atoms);
-            // here size x is doubled
-            box_config.box_size_.0 /= 2;
+            box_config.box_size_ = match box_config.lattice {
+                LatticeType::Bcc => {
+                    let mut sizes = auto_get_box_size(atoms, box_config.lattice_const);
+                    // here size x is doubled
+                    sizes.0 /= 2;
+                    sizes
+                }
+                LatticeType::Fcc => auto_get_fcc_box_size(atoms, box_config.lattice_const),
+            };
         }
     } else {
         box_config.box_size_ = (box_config.input_box_size[0] as usize, box_config.input_box_size[1] as usize, box_config.input_box_size[2] as usize);
@@ -43,7 +95,7 @@ pub fn config_simulation_box(snapshot: &xyz_reader::Snapshot<Atom>, box_config:
         println!("bad box size");
         return false;
     }
-    if 2 * box_size_x * box_size_y * box_size_z != atoms_size {
+    if atoms_per_cell * box_size_x * box_size_y * box_size_z != atoms_size {
         println!("Warning: box size ({},{},{}) not match atoms size.", box_size_x, box_size_y, box_size_z);
         return false;
     }
@@ -62,20 +114,16 @@ pub fn config_simulation_box(snapshot: &xyz_reader::Snapshot<Atom>, box_config:
     return true;
 }
 
-// By passing the atoms position list,
-// then we can get the box size of simulation box, which can be used to calculating 1D lattice index.
-// If the box size in some dimension is not as desired, 0 will be return in the dimension.
-fn auto_get_box_size(atoms: &Vec<xyzio::Atom>) -> (usize, usize, usize) {
+// get the extent (max position - min position) of atoms in each dimension.
+fn positions_extent<A: AnsAtom>(atoms: &[A]) -> (f32, f32, f32) {
     let mut x_min = f32::INFINITY; // todo can use f64 as float
     let mut y_min = f32::INFINITY;
     let mut z_min = f32::INFINITY;
     let mut x_max = f32::NEG_INFINITY;
     let mut y_max = f32::NEG_INFINITY;
     let mut z_max = f32::NEG_INFINITY;
-    for i in 0..atoms.len() {
-        let x = atoms[i].x;
-        let y = atoms[i].y;
-        let z = atoms[i].z;
+    for atom in atoms {
+        let (x, y, z) = atom.position();
 
         if x < x_min {
             x_min = x;
@@ -99,7 +147,15 @@ fn auto_get_box_size(atoms: &Vec<xyzio::Atom>) -> (usize, usize, usize) {
         }
     }
 
-    let (size_x_, size_y_, size_z_) = voronoy::voronoy(x_max - x_min, y_max - y_min, z_max - z_min);
+    (x_max - x_min, y_max - y_min, z_max - z_min)
+}
+
+// By passing the atoms position list,
+// then we can get the box size of simulation box, which can be used to calculating 1D lattice index.
+// If the box size in some dimension is not as desired, 0 will be return in the dimension.
+fn auto_get_box_size<A: AnsAtom>(atoms: &[A], lattice_const: voronoy::Float) -> (usize, usize, usize) {
+    let (extent_x, extent_y, extent_z) = positions_extent(atoms);
+    let (size_x_, size_y_, size_z_) = voronoy::voronoy(extent_x, extent_y, extent_z, lattice_const);
     let mut sizes = (0, 0, 0);
 
     if size_x_ < 0 {
@@ -121,6 +177,76 @@ fn auto_get_box_size(atoms: &Vec<xyzio::Atom>) -> (usize, usize, usize) {
     return sizes;
 }
 
+// get box size (in unit of lattice cells) of FCC lattice by atoms positions.
+// In a perfect FCC box with n cells, the extent of positions is n-0.5 lattice constant (2n-1 in half lattice unit).
+fn auto_get_fcc_box_size<A: AnsAtom>(atoms: &[A], lattice_const: voronoy::Float) -> (usize, usize, usize) {
+    let (extent_x, extent_y, extent_z) = positions_extent(atoms);
+    let half_lattice = lattice_const / 2.0;
+    let size = |extent: f32| ((extent / half_lattice).round() as usize + 1) / 2;
+    (size(extent_x), size(extent_y), size(extent_z))
+}
+
+/**
+ * estimate lattice constant from atoms.
+ * If the simulation box is given in the input file (`Lattice=` of extxyz file) and it is periodic in all dimensions,
+ * the lattice constant is computed from the box volume and the atoms number (cells number).
+ * Otherwise, it is fitted from the atoms number and the length of each dimension:
+ * the box length (n lattice constant with n cells) in periodic dimensions of the box, and the extent of atoms positions
+ * in free dimensions, where the box is only a container (in a perfect BCC or FCC box, the extent is (n-0.5) lattice constant).
+ */
+fn estimate_lattice_const<A: AnsAtom>(atoms: &[A], sim_box: Option<&SimBox>, box_config: &BoxConfig) -> Option<voronoy::Float> {
+    let cells = (atoms.len() / box_config.lattice.atoms_per_cell()) as f64;
+    if cells == 0.0 {
+        return None;
+    }
+    let periodic = |sim_box: &SimBox, d: usize| sim_box.pbc[d] && box_config.periodic[d];
+    if let Some(sim_box) = sim_box {
+        if (0..3).all(|d| periodic(sim_box, d)) {
+            return Some((sim_box.volume() / cells).cbrt() as voronoy::Float);
+        }
+    }
+
+    let (extent_x, extent_y, extent_z) = positions_extent(atoms);
+    // length and cells offset of each dimension: length = (n - offset) * a.
+    let mut lengths = [(extent_x as f64, 0.5), (extent_y as f64, 0.5), (extent_z as f64, 0.5)];
+    if let Some(sim_box) = sim_box {
+        for d in (0..3).filter(|&d| periodic(sim_box, d)) {
+            lengths[d] = (sim_box.cell[d][d], 0.0);
+        }
+    }
+    if box_config.input_box_size.len() == 3 {
+        // box size is known, average of each dimension.
+        let sum: f64 = (0..3).map(|d| lengths[d].0 / (box_config.input_box_size[d] as f64 - lengths[d].1)).sum();
+        return Some((sum / 3.0) as voronoy::Float);
+    }
+    fit_lattice_const(lengths, cells).map(|a| a as voronoy::Float)
+}
+
+// solve lattice constant `a` of equation: (l_x/a + o_x) * (l_y/a + o_y) * (l_z/a + o_z) = cells, by bisection,
+// where `lengths` are pairs of length `l` and offset `o` of each dimension.
+// The left side is monotone decreasing with `a`.
+fn fit_lattice_const(lengths: [(f64, f64); 3], cells: f64) -> Option<f64> {
+    let cells_of = |a: f64| lengths.iter().map(|(l, o)| l / a + o).product::<f64>();
+    let max_extent = lengths.iter().map(|(l, _)| *l).fold(0.0, f64::max);
+    if !max_extent.is_finite() || max_extent <= 0.0 || cells < 1.0 {
+        return None;
+    }
+    // cells_of(low) > cells, cells_of(high) <= cells.
+    let (mut low, mut high) = (max_extent * 1e-6, max_extent * 2.0);
+    if cells_of(low) < cells {
+        return None;
+    }
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if cells_of(mid) > cells {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
 // return cube root of a positive integer number.
 fn cube_root(n: usize) -> usize {
     let mut low = 1 as usize;
@@ -160,11 +286,55 @@ mod get_box_size_tests {
             z: 9.0 * voronoy::LATTICE_CONST,
         });
         // get lattice size in each dimension
-        let sizes = auto_get_box_size(&atoms);
+        let sizes = auto_get_box_size(&atoms, voronoy::LATTICE_CONST);
         assert_eq!(sizes.0, 2 * 2);
         assert_eq!(sizes.1, 5);
         assert_eq!(sizes.2, 1);
     }
+
+    #[test]
+    fn test_get_fcc_box_size() {
+        let a = voronoy::FCC_LATTICE_CONST;
+        let atoms = vec![
+            xyzio::Atom { element: String::from("Cu"), x: 0.0, y: 0.0, z: 0.0 },
+            xyzio::Atom { element: String::from("Cu"), x: 3.5 * a, y: 1.5 * a, z: 4.5 * a },
+        ];
+        assert_eq!(auto_get_fcc_box_size(&atoms, a), (4, 2, 5));
+    }
+}
+
+#[cfg(test)]
+mod lattice_const_tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_lattice_const() {
+        // 3*4*5 cells, extent is (n-0.5)*a.
+        let a = 3.52;
+        let lengths = [(2.5 * a, 0.5), (3.5 * a, 0.5), (4.5 * a, 0.5)];
+        assert!((fit_lattice_const(lengths, 60.0).unwrap() - a).abs() < 1e-9);
+        assert!(fit_lattice_const([(0.0, 0.5); 3], 60.0).is_none());
+        // periodic in z: the box length is n*a.
+        let lengths = [(2.5 * a, 0.5), (3.5 * a, 0.5), (5.0 * a, 0.0)];
+        assert!((fit_lattice_const(lengths, 60.0).unwrap() - a).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_estimate_from_header() {
+        let atoms: Vec<xyzio::Atom> = (0..16).map(|_| xyzio::Atom { element: String::from("Ni"), x: 0.0, y: 0.0, z: 0.0 }).collect();
+        let sim_box = SimBox::from_extxyz_comment("Lattice=\"7.04 0 0 0 7.04 0 0 0 3.52\" Properties=species:S:1:pos:R:3");
+        let box_config = BoxConfig { auto_lattice_const: true, ..BoxConfig::for_test(LatticeType::Fcc, (0, 0, 0), 0.0) };
+        assert!((estimate_lattice_const(&atoms, sim_box.as_ref(), &box_config).unwrap() - 3.52).abs() < 1e-5);
+
+        // free boundaries: the box is only a container of atoms (2x2x1 cells), and the extent of positions is used.
+        let a: voronoy::Float = 3.52;
+        let atoms: Vec<xyzio::Atom> = (0..16).map(|i| {
+            let x = if i % 2 == 0 { 0.0 } else { 1.5 * a };
+            xyzio::Atom { element: String::from("Ni"), x, y: x, z: x / 3.0 }
+        }).collect();
+        let sim_box = SimBox::from_extxyz_comment("Lattice=\"20 0 0 0 20 0 0 0 20\" pbc=\"F F F\"");
+        assert!((estimate_lattice_const(&atoms, sim_box.as_ref(), &box_config).unwrap() - a).abs() < 1e-5);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,204 @@
+// Clustering of defects (vacancies and interstitials) found by Wigner-Seitz analysis.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use crate::ans::box_config::BoxConfig;
+use crate::ans::voronoy::{Float, Inx};
+use crate::ans::ws_result::WsResult;
+
+const CSV_HEADER: &str = "cluster,size,type,vacancies,interstitials,centroid:x,centroid:y,centroid:z\n";
+
+// a cluster of defect sites connected by neighbor lattices.
+pub struct Cluster {
+    pub id: usize,
+    pub vacancies: usize,
+    pub interstitials: usize,
+    // centroid of vacancy sites and interstitial atoms.
+    pub centroid: (Float, Float, Float),
+}
+
+impl Cluster {
+    pub fn size(&self) -> usize {
+        self.vacancies + self.interstitials
+    }
+
+    pub fn kind(&self) -> &'static str {
+        if self.interstitials == 0 {
+            "vacancy"
+        } else if self.vacancies == 0 {
+            "SIA"
+        } else {
+            "mixed"
+        }
+    }
+}
+
+// a lattice site with defects: a vacancy or interstitials.
+struct DefectSite {
+    coord: (Inx, Inx, Inx),
+    vacancy: bool,
+    // position of vacancy site or interstitial atoms.
+    positions: Vec<(Float, Float, Float)>,
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/**
+ * find connected components of defect sites, two defect sites are connected if one is in the
+ * 1st to `shell`-th nearest neighbor shells of the other.
+ * Clusters are sorted by size (descending), and the cluster id is the index after sorting.
+ */
+pub fn find_clusters(result: &WsResult, box_config: &BoxConfig, shell: usize) -> Vec<Cluster> {
+    let lattice = box_config.lattice;
+    let box_size = box_config.box_size_;
+
+    let mut sites: Vec<DefectSite> = Vec::new();
+    let mut site_ids: HashMap<Inx, usize> = HashMap::new();
+    for vacancy in &result.vacancies {
+        site_ids.insert(vacancy.index, sites.len());
+        sites.push(DefectSite { coord: vacancy.coord, vacancy: true, positions: vec![vacancy.position] });
+    }
+    for inter in &result.interstitials {
+        let id = *site_ids.entry(inter.site.index).or_insert_with(|| {
+            sites.push(DefectSite { coord: inter.site.coord, vacancy: false, positions: Vec::new() });
+            sites.len() - 1
+        });
+        sites[id].positions.push(inter.position);
+    }
+
+    // union sites with defect neighbors.
+    let mut parents: Vec<usize> = (0..sites.len()).collect();
+    let offsets = lattice.neighbor_offsets(shell);
+    for (i, site) in sites.iter().enumerate() {
+        let half = lattice.half_coordinate(site.coord);
+        for offset in &offsets {
+            let neighbor = lattice.lattice_coordinate([half[0] + offset[0], half[1] + offset[1], half[2] + offset[2]]);
+            let neighbor = match lattice.wrap(neighbor, box_size, box_config.periodic) {
+                Some(neighbor) => neighbor,
+                None => continue,
+            };
+            if let Some(&j) = site_ids.get(&lattice.index(neighbor, box_size)) {
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[root_i] = root_j;
+            }
+        }
+    }
+
+    // group sites by root.
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..sites.len() {
+        let root = find_root(&mut parents, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    // box length in each dimension, used for computing centroid across periodic boundaries.
+    let lengths = [box_size.0 as Float * box_config.lattice_const, box_size.1 as Float * box_config.lattice_const,
+        box_size.2 as Float * box_config.lattice_const];
+    let mut clusters: Vec<Cluster> = groups.values().map(|members| {
+        let mut cluster = Cluster { id: 0, vacancies: 0, interstitials: 0, centroid: (0.0, 0.0, 0.0) };
+        let reference = sites[members[0]].positions[0];
+        let reference = [reference.0, reference.1, reference.2];
+        let mut sum = [0.0; 3];
+        let mut count = 0;
+        for &m in members {
+            if sites[m].vacancy {
+                cluster.vacancies += 1;
+            } else {
+                cluster.interstitials += sites[m].positions.len();
+            }
+            for p in &sites[m].positions {
+                // minimum image of the position relative to the reference position.
+                for (d, v) in [p.0, p.1, p.2].iter().enumerate() {
+                    let mut delta = v - reference[d];
+                    if box_config.periodic[d] {
+                        delta -= lengths[d] * (delta / lengths[d]).round();
+                    }
+                    sum[d] += reference[d] + delta;
+                }
+                count += 1;
+            }
+        }
+        let count = count as Float;
+        cluster.centroid = (sum[0] / count, sum[1] / count, sum[2] / count);
+        cluster
+    }).collect();
+
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.size()));
+    for (id, cluster) in clusters.iter_mut().enumerate() {
+        cluster.id = id;
+    }
+    clusters
+}
+
+// cluster size -> clusters number.
+pub fn size_distribution(clusters: &[Cluster]) -> BTreeMap<usize, usize> {
+    let mut distribution = BTreeMap::new();
+    for cluster in clusters {
+        *distribution.entry(cluster.size()).or_insert(0) += 1;
+    }
+    distribution
+}
+
+pub fn write_csv<W: Write>(writer: &mut W, clusters: &[Cluster]) -> io::Result<()> {
+    writer.write_all(CSV_HEADER.as_bytes())?;
+    for c in clusters {
+        writeln!(writer, "{},{},{},{},{},{},{},{}", c.id, c.size(), c.kind(), c.vacancies, c.interstitials,
+                 c.centroid.0, c.centroid.1, c.centroid.2)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ans::lattice::LatticeType;
+    use crate::ans::ws_result::{Interstitial, Site};
+
+    fn make_site(lattice: LatticeType, coord: (Inx, Inx, Inx), box_size: (usize, usize, usize)) -> Site {
+        Site { index: lattice.index(coord, box_size), coord, position: lattice.site_position(coord, 1.0) }
+    }
+
+    #[test]
+    fn test_find_clusters() {
+        let lattice = LatticeType::Bcc;
+        let box_size = (4, 4, 4);
+        let box_config = BoxConfig { cluster_shell: Some(1), ..BoxConfig::for_test(lattice, box_size, 1.0) };
+        let result = WsResult {
+            atoms: 128,
+            sites: 128,
+            skipped_atoms: 0,
+            // (0, 0, 0) and (7, 3, 3) are 1NN across periodic boundary, (4, 2, 2) is 2NN of (6, 2, 2).
+            vacancies: vec![make_site(lattice, (0, 0, 0), box_size), make_site(lattice, (7, 3, 3), box_size),
+                            make_site(lattice, (4, 2, 2), box_size)],
+            interstitials: vec![Interstitial {
+                site: make_site(lattice, (6, 2, 2), box_size),
+                atom_index: 0,
+                atom_id: None,
+                element: String::from("Fe"),
+                position: (3.0, 2.2, 2.0),
+                dumbbell: None,
+            }],
+            occupancy: BTreeMap::new(),
+        };
+
+        let clusters = find_clusters(&result, &box_config, 1);
+        assert_eq!(clusters.len(), 3);
+        assert_eq!(clusters[0].size(), 2);
+        assert_eq!(clusters[0].kind(), "vacancy");
+        // centroid of (0, 0, 0) and (-0.5, -0.5, -0.5).
+        let (x, y, z) = clusters[0].centroid;
+        assert!((x + 0.25).abs() < 1e-6 && (y + 0.25).abs() < 1e-6 && (z + 0.25).abs() < 1e-6);
+
+        let clusters = find_clusters(&result, &box_config, 2);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[1].kind(), "mixed");
+        assert_eq!(size_distribution(&clusters).get(&2), Some(&2));
+    }
+}
@@ -0,0 +1,218 @@
+// Structure identification of atoms by adaptive common neighbor analysis (a-CNA).
+// see also: A. Stukowski, Modelling Simul. Mater. Sci. Eng. 20, 045021 (2012).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use rayon::prelude::*;
+
+use crate::ans::ans_atom::AnsAtom;
+use crate::ans::neighbor;
+use crate::ans::per_atom::{self, PerAtomAnalysis};
+use crate::json;
+use crate::xyz::sim_box::SimBox;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Structure {
+    Other,
+    Fcc,
+    Hcp,
+    Bcc,
+    Ico,
+}
+
+pub const STRUCTURES: [Structure; 5] = [Structure::Other, Structure::Fcc, Structure::Hcp, Structure::Bcc, Structure::Ico];
+
+impl fmt::Display for Structure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Structure::Other => "other",
+            Structure::Fcc => "fcc",
+            Structure::Hcp => "hcp",
+            Structure::Bcc => "bcc",
+            Structure::Ico => "ico",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/**
+ * CNA signature of the bond between the central atom and each neighbor:
+ * (number of common neighbors, number of bonds between common neighbors, number of bonds in the longest chain).
+ * `neighbors` are vectors from the central atom, two neighbors are bonded if their distance is not larger than cutoff.
+ */
+fn signatures(neighbors: &[[f64; 3]], cutoff: f64) -> Vec<(usize, usize, usize)> {
+    let n = neighbors.len();
+    let cutoff2 = cutoff * cutoff;
+    let mut bonded = vec![vec![false; n]; n];
+    for j in 0..n {
+        for k in (j + 1)..n {
+            let (a, b) = (neighbors[j], neighbors[k]);
+            let dist2 = (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2);
+            bonded[j][k] = dist2 <= cutoff2;
+            bonded[k][j] = bonded[j][k];
+        }
+    }
+
+    bonded.iter().map(|bonds_of_j| {
+        let common: Vec<usize> = (0..n).filter(|&k| bonds_of_j[k]).collect();
+        // bonds between common neighbors, the connected bonds make a chain.
+        let mut parents: Vec<usize> = (0..common.len()).collect();
+        let mut bonds = Vec::new();
+        for a in 0..common.len() {
+            for b in (a + 1)..common.len() {
+                if bonded[common[a]][common[b]] {
+                    bonds.push(a);
+                    let (root_a, root_b) = (find_root(&mut parents, a), find_root(&mut parents, b));
+                    parents[root_a] = root_b;
+                }
+            }
+        }
+        let mut chains: BTreeMap<usize, usize> = BTreeMap::new();
+        for &a in &bonds {
+            *chains.entry(find_root(&mut parents, a)).or_insert(0) += 1;
+        }
+        let longest_chain = chains.values().cloned().max().unwrap_or(0);
+        (common.len(), bonds.len(), longest_chain)
+    }).collect()
+}
+
+/**
+ * identify structure of an atom by its nearest neighbors (vectors from the atom, sorted by distance).
+ * The cutoff is determined by the local lattice scale:
+ * for FCC, HCP and ICO, the mean distance of 12 nearest neighbors, and for BCC, the 14 nearest neighbors.
+ */
+pub fn identify(nearest: &[[f64; 3]]) -> Structure {
+    let norm = |v: &[f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let cutoff_factor = (1.0 + 2.0_f64.sqrt()) / 2.0;
+
+    if nearest.len() >= 12 {
+        let scale = nearest[..12].iter().map(norm).sum::<f64>() / 12.0;
+        let signatures = signatures(&nearest[..12], scale * cutoff_factor);
+        let count = |s: (usize, usize, usize)| signatures.iter().filter(|&&t| t == s).count();
+        let (n421, n422, n555) = (count((4, 2, 1)), count((4, 2, 2)), count((5, 5, 5)));
+        if n421 == 12 {
+            return Structure::Fcc;
+        }
+        if n421 == 6 && n422 == 6 {
+            return Structure::Hcp;
+        }
+        if n555 == 12 {
+            return Structure::Ico;
+        }
+    }
+    if nearest.len() >= 14 {
+        // the 1st neighbor shell is at sqrt(3)/2 lattice constant, and the 2nd shell is at lattice constant.
+        let scale = (nearest[..8].iter().map(norm).sum::<f64>() / (3.0_f64.sqrt() / 2.0)
+            + nearest[8..14].iter().map(norm).sum::<f64>()) / 14.0;
+        let signatures = signatures(&nearest[..14], scale * cutoff_factor);
+        let count = |s: (usize, usize, usize)| signatures.iter().filter(|&&t| t == s).count();
+        if count((6, 6, 6)) == 8 && count((4, 4, 4)) == 6 {
+            return Structure::Bcc;
+        }
+    }
+    Structure::Other
+}
+
+/**
+ * identify structure of each atom by adaptive CNA.
+ * If `sim_box` is `None`, atoms are in a box with free boundaries covering all atoms.
+ */
+pub fn adaptive_cna(positions: &[[f64; 3]], sim_box: Option<SimBox>) -> Vec<Structure> {
+    neighbor::nearest_neighbors(positions, sim_box, 14).par_iter().map(|nearest| identify(nearest)).collect()
+}
+
+// structure -> atoms number, all structure types are included.
+pub fn structure_counts(structures: &[Structure]) -> BTreeMap<Structure, usize> {
+    let mut counts: BTreeMap<Structure, usize> = STRUCTURES.iter().map(|&s| (s, 0)).collect();
+    for s in structures {
+        *counts.entry(*s).or_insert(0) += 1;
+    }
+    counts
+}
+
+// structure identification by adaptive CNA, the structure of each atom is written as column `structure`,
+// and the count of each structure type is written to the summary.
+pub struct Cna;
+
+impl PerAtomAnalysis for Cna {
+    fn analyse<A: AnsAtom>(&self, atoms: &[A], sim_box: Option<SimBox>, output: &str) -> Result<(), String> {
+        let structures = adaptive_cna(&per_atom::positions(atoms), sim_box);
+        let counts = structure_counts(&structures);
+        let counts_str: Vec<String> = counts.iter().map(|(s, n)| format!("{}: {}", s, n)).collect();
+        println!("{}", counts_str.join(", "));
+        per_atom::write_files(output, |writer| per_atom::write_csv(writer, atoms, "structure", &structures),
+                              |writer| write_json_summary(writer, &counts))
+    }
+}
+
+pub fn write_json_summary<W: Write>(writer: &mut W, counts: &BTreeMap<Structure, usize>) -> io::Result<()> {
+    let atoms: usize = counts.values().sum();
+    let structures = json::object(counts.iter().map(|(s, n)| (s, n.to_string())));
+    writeln!(writer, "{}", json::pretty_object(vec![("atoms", atoms.to_string()), ("structures", structures)], 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // positions of a lattice by the basis (in unit of cell) and cells number.
+    fn make_lattice(basis: &[[f64; 3]], cell: [f64; 3], n: [usize; 3]) -> Vec<[f64; 3]> {
+        let mut positions = Vec::new();
+        for z in 0..n[2] {
+            for y in 0..n[1] {
+                for x in 0..n[0] {
+                    for b in basis {
+                        positions.push([(x as f64 + b[0]) * cell[0], (y as f64 + b[1]) * cell[1],
+                            (z as f64 + b[2]) * cell[2]]);
+                    }
+                }
+            }
+        }
+        positions
+    }
+
+    fn periodic_box(cell: [f64; 3], n: [usize; 3]) -> Option<SimBox> {
+        SimBox::orthogonal((cell[0] * n[0] as f64, cell[1] * n[1] as f64, cell[2] * n[2] as f64), [0.0; 3])
+    }
+
+    #[test]
+    fn test_perfect_lattices() {
+        let bcc = make_lattice(&[[0.0, 0.0, 0.0], [0.5, 0.5, 0.5]], [2.855; 3], [4, 4, 4]);
+        let structures = adaptive_cna(&bcc, periodic_box([2.855; 3], [4, 4, 4]));
+        assert!(structures.iter().all(|&s| s == Structure::Bcc));
+        // in a box smaller than the cutoff, images are counted.
+        let structures = adaptive_cna(&bcc[..2], periodic_box([2.855; 3], [1, 1, 1]));
+        assert_eq!(structures, vec![Structure::Bcc; 2]);
+
+        let fcc_basis = [[0.0, 0.0, 0.0], [0.5, 0.5, 0.0], [0.5, 0.0, 0.5], [0.0, 0.5, 0.5]];
+        let fcc = make_lattice(&fcc_basis, [3.615; 3], [3, 3, 3]);
+        let structures = adaptive_cna(&fcc, periodic_box([3.615; 3], [3, 3, 3]));
+        assert_eq!(structure_counts(&structures).get(&Structure::Fcc), Some(&108));
+
+        // orthogonal cell of HCP lattice: a, sqrt(3)*a, c.
+        let a = 2.5;
+        let cell = [a, 3.0_f64.sqrt() * a, (8.0_f64 / 3.0).sqrt() * a];
+        let hcp_basis = [[0.0, 0.0, 0.0], [0.5, 0.5, 0.0], [0.5, 1.0 / 6.0, 0.5], [0.0, 2.0 / 3.0, 0.5]];
+        let hcp = make_lattice(&hcp_basis, cell, [4, 2, 2]);
+        let structures = adaptive_cna(&hcp, periodic_box(cell, [4, 2, 2]));
+        assert!(structures.iter().all(|&s| s == Structure::Hcp));
+
+        // free boundaries: atoms on surface are not identified.
+        let structures = adaptive_cna(&bcc, None);
+        let counts = structure_counts(&structures);
+        assert_eq!(counts.get(&Structure::Bcc), Some(&(2 * 2 * 2 * 2)));
+        let mut out: Vec<u8> = Vec::new();
+        write_json_summary(&mut out, &counts).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("\"atoms\": 128,\n"));
+    }
+}
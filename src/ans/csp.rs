@@ -0,0 +1,102 @@
+// Centrosymmetry parameter (CSP) of atoms, it is zero for atoms in perfect centrosymmetric lattices (BCC, FCC),
+// and large for atoms near defects, dislocations and surfaces.
+
+use std::io::{self, Write};
+
+use rayon::prelude::*;
+
+use crate::ans::ans_atom::AnsAtom;
+use crate::ans::neighbor;
+use crate::ans::per_atom::{self, PerAtomAnalysis};
+use crate::json;
+use crate::xyz::sim_box::SimBox;
+
+/**
+ * CSP of an atom by its `n` nearest neighbors (vectors from the atom):
+ * for all pairs of neighbors (i, j), compute |R_i + R_j|^2, then sum the n/2 smallest values.
+ * If the atom has less than `n` neighbors, the CSP is 0 (the same as LAMMPS).
+ */
+pub fn centro_symmetry(nearest: &[[f64; 3]], n: usize) -> f64 {
+    if nearest.len() < n {
+        return 0.0;
+    }
+    let mut pairs = Vec::with_capacity(n * (n - 1) / 2);
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let (a, b) = (nearest[i], nearest[j]);
+            pairs.push((a[0] + b[0]).powi(2) + (a[1] + b[1]).powi(2) + (a[2] + b[2]).powi(2));
+        }
+    }
+    pairs.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    pairs.iter().take(n / 2).sum()
+}
+
+pub fn compute_csp(positions: &[[f64; 3]], sim_box: Option<SimBox>, n: usize) -> Vec<f64> {
+    neighbor::nearest_neighbors(positions, sim_box, n).par_iter().map(|nearest| centro_symmetry(nearest, n)).collect()
+}
+
+// centrosymmetry parameter by `neighbors` nearest neighbors (8 for BCC, 12 for FCC),
+// the CSP of each atom is written as column `csp`.
+pub struct Csp {
+    pub neighbors: usize,
+}
+
+impl PerAtomAnalysis for Csp {
+    fn analyse<A: AnsAtom>(&self, atoms: &[A], sim_box: Option<SimBox>, output: &str) -> Result<(), String> {
+        let values = compute_csp(&per_atom::positions(atoms), sim_box, self.neighbors);
+        let (mean, max) = statistics(&values);
+        println!("CSP mean: {}, max: {}", mean, max);
+        per_atom::write_files(output, |writer| per_atom::write_csv(writer, atoms, "csp", &values),
+                              |writer| write_json_summary(writer, self.neighbors, &values))
+    }
+}
+
+// mean and max value of CSP.
+fn statistics(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    (mean, values.iter().cloned().fold(0.0, f64::max))
+}
+
+pub fn write_json_summary<W: Write>(writer: &mut W, neighbors: usize, values: &[f64]) -> io::Result<()> {
+    let (mean, max) = statistics(values);
+    let items = vec![
+        ("atoms", values.len().to_string()),
+        ("neighbors", neighbors.to_string()),
+        ("mean", json::number(mean)),
+        ("max", json::number(max)),
+    ];
+    writeln!(writer, "{}", json::pretty_object(items, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_centro_symmetry() {
+        let a = 2.855;
+        let bcc: Vec<[f64; 3]> = (0..128).map(|i| {
+            let (cell, b) = (i / 2, (i % 2) as f64 * 0.5);
+            [((cell % 4) as f64 + b) * a, ((cell / 4 % 4) as f64 + b) * a, ((cell / 16) as f64 + b) * a]
+        }).collect();
+        let sim_box = SimBox::orthogonal((4.0 * a, 4.0 * a, 4.0 * a), [0.0; 3]);
+        assert!(compute_csp(&bcc, sim_box, 8).iter().all(|&csp| csp < 1e-9));
+
+        // move one atom, the CSP of its neighbors is no longer zero.
+        let mut displaced = bcc.clone();
+        displaced[0][0] += 0.2;
+        let values = compute_csp(&displaced, sim_box, 8);
+        assert!(values[1] > 0.01);
+        assert!(values[64] < 1e-9);
+
+        // surface atoms in free boundaries.
+        let values = compute_csp(&bcc, None, 8);
+        assert!(values[0] > 1.0);
+
+        // missing neighbors
+        assert_eq!(centro_symmetry(&[[1.0, 0.0, 0.0]], 8), 0.0);
+    }
+}
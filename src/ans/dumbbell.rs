@@ -0,0 +1,98 @@
+// Orientation of self-interstitial dumbbells: the vector between two atoms sharing a lattice site.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ans::voronoy::Float;
+use crate::ans::ws_result::Interstitial;
+
+// max angle (in degree) between the dumbbell vector and a lattice direction.
+// The min angle between <100>, <110> and <111> directions is 35.26 degree (between <110> and <111>).
+const ANGLE_TOLERANCE: Float = 15.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Orientation {
+    D100,
+    D110,
+    D111,
+    // not close to any of the directions above.
+    Other,
+}
+
+// two atoms sharing a lattice site.
+#[derive(Copy, Clone)]
+pub struct Dumbbell {
+    // vector from the atom occupying the site to the interstitial atom.
+    pub vector: (Float, Float, Float),
+    pub orientation: Orientation,
+}
+
+impl Dumbbell {
+    pub fn new(vector: (Float, Float, Float)) -> Dumbbell {
+        Dumbbell { vector, orientation: classify(vector) }
+    }
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Orientation::D100 => "<100>",
+            Orientation::D110 => "<110>",
+            Orientation::D111 => "<111>",
+            Orientation::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/**
+ * classify the dumbbell vector against <100>, <110> and <111> lattice directions.
+ * The crystal axes are assumed to be aligned with the box axes.
+ * Crowdions are also classified as <111>, since their atoms are aligned along <111>.
+ */
+pub fn classify(vector: (Float, Float, Float)) -> Orientation {
+    // sort absolute components in descending order, then compare with (1,0,0), (1,1,0) and (1,1,1).
+    let mut v = [vector.0.abs(), vector.1.abs(), vector.2.abs()];
+    v.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+    let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if norm == 0.0 {
+        return Orientation::Other;
+    }
+    let candidates = [
+        (Orientation::D100, v[0]),
+        (Orientation::D110, (v[0] + v[1]) / (2.0 as Float).sqrt()),
+        (Orientation::D111, (v[0] + v[1] + v[2]) / (3.0 as Float).sqrt()),
+    ];
+    let min_cos = ANGLE_TOLERANCE.to_radians().cos();
+    candidates.iter()
+        .map(|&(orientation, dot)| (orientation, dot / norm))
+        .filter(|&(_, cos)| cos >= min_cos)
+        .fold(None, |best: Option<(Orientation, Float)>, c| match best {
+            Some(b) if b.1 >= c.1 => Some(b),
+            _ => Some(c),
+        })
+        .map_or(Orientation::Other, |(orientation, _)| orientation)
+}
+
+// orientation -> dumbbells number.
+pub fn orientation_histogram(interstitials: &[Interstitial]) -> BTreeMap<Orientation, usize> {
+    let mut histogram = BTreeMap::new();
+    for orientation in interstitials.iter().filter_map(|inter| inter.dumbbell.map(|d| d.orientation)) {
+        *histogram.entry(orientation).or_insert(0) += 1;
+    }
+    histogram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify((0.0, 0.0, -1.2)), Orientation::D100);
+        assert_eq!(classify((1.0, -0.95, 0.1)), Orientation::D110);
+        assert_eq!(classify((-1.0, 1.0, 1.05)), Orientation::D111);
+        assert_eq!(classify((1.0, 0.5, 0.0)), Orientation::Other);
+        assert_eq!(classify((0.0, 0.0, 0.0)), Orientation::Other);
+    }
+}
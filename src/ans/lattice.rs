@@ -0,0 +1,245 @@
+// Lattice types supported by Wigner-Seitz defect analysis, and the mapping between lattice coordinate and lattice index.
+
+use crate::ans::voronoy::{self, Float, Inx};
+
+/**
+ * For BCC lattice, the lattice coordinate is in unit of lattice constant, but x is doubled:
+ * corner site (i, j, k) has coordinate (2i, j, k), body center site (i+0.5, j+0.5, k+0.5) has coordinate (2i+1, j, k).
+ *
+ * For FCC lattice, the lattice coordinate is in unit of half lattice constant,
+ * and only the coordinates (i, j, k) with i+j+k even are lattice sites.
+ */
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LatticeType {
+    Bcc,
+    Fcc,
+}
+
+impl LatticeType {
+    pub fn atoms_per_cell(&self) -> usize {
+        match self {
+            LatticeType::Bcc => 2,
+            LatticeType::Fcc => 4,
+        }
+    }
+
+    // lattice constant used if it is not specified by user.
+    pub fn default_lattice_const(&self) -> Float {
+        match self {
+            LatticeType::Bcc => voronoy::LATTICE_CONST,
+            LatticeType::Fcc => voronoy::FCC_LATTICE_CONST,
+        }
+    }
+
+    // lattice coordinate of the nearest lattice site (Wigner-Seitz cell) of a position.
+    pub fn nearest_site(&self, x: Float, y: Float, z: Float, lattice_const: Float) -> (Inx, Inx, Inx) {
+        match self {
+            LatticeType::Bcc => voronoy::voronoy(x, y, z, lattice_const),
+            LatticeType::Fcc => voronoy::voronoy_fcc(x, y, z, lattice_const),
+        }
+    }
+
+    // ideal position of a lattice site (relative to the box start), it is the inverse of `nearest_site`.
+    pub fn site_position(&self, (x, y, z): (Inx, Inx, Inx), lattice_const: Float) -> (Float, Float, Float) {
+        let half = lattice_const / 2.0;
+        match self {
+            LatticeType::Bcc => {
+                // body center site has odd x, and it is shifted by half lattice in y and z.
+                let shift = x.rem_euclid(2) as Float * half;
+                (x as Float * half, y as Float * lattice_const + shift, z as Float * lattice_const + shift)
+            }
+            LatticeType::Fcc => (x as Float * half, y as Float * half, z as Float * half),
+        }
+    }
+
+    /**
+     * lattice coordinate to the coordinate in unit of half lattice constant.
+     * For FCC lattice, they are the same.
+     * For BCC lattice, x is already in half lattice unit, and body center site (odd x) is shifted in y and z.
+     */
+    pub fn half_coordinate(&self, (x, y, z): (Inx, Inx, Inx)) -> [Inx; 3] {
+        match self {
+            LatticeType::Bcc => {
+                let shift = x.rem_euclid(2);
+                [x, 2 * y + shift, 2 * z + shift]
+            }
+            LatticeType::Fcc => [x, y, z],
+        }
+    }
+
+    // the inverse of `half_coordinate`, the input must be a lattice site.
+    pub fn lattice_coordinate(&self, [x, y, z]: [Inx; 3]) -> (Inx, Inx, Inx) {
+        match self {
+            LatticeType::Bcc => {
+                let shift = x.rem_euclid(2);
+                (x, (y - shift).div_euclid(2), (z - shift).div_euclid(2))
+            }
+            LatticeType::Fcc => (x, y, z),
+        }
+    }
+
+    /**
+     * offsets (in half lattice unit) of neighbor sites up to the `shell`-th nearest neighbor shell (1, 2 or 3).
+     * Squared distances of the first 3 shells in half lattice unit are 3, 4, 8 for BCC, and 2, 4, 6 for FCC.
+     */
+    pub fn neighbor_offsets(&self, shell: usize) -> Vec<[Inx; 3]> {
+        let cutoffs = match self {
+            LatticeType::Bcc => [3, 4, 8],
+            LatticeType::Fcc => [2, 4, 6],
+        };
+        let cutoff = cutoffs[shell.clamp(1, 3) - 1];
+        let range: [Inx; 5] = [-2, -1, 0, 1, 2];
+        let mut offsets = Vec::new();
+        for &dx in range.iter() {
+            for &dy in range.iter() {
+                for &dz in range.iter() {
+                    let is_site = match self {
+                        LatticeType::Bcc => dx.rem_euclid(2) == dy.rem_euclid(2) && dy.rem_euclid(2) == dz.rem_euclid(2),
+                        LatticeType::Fcc => (dx + dy + dz) % 2 == 0,
+                    };
+                    let dist2 = dx * dx + dy * dy + dz * dz;
+                    if is_site && dist2 != 0 && dist2 <= cutoff {
+                        offsets.push([dx, dy, dz]);
+                    }
+                }
+            }
+        }
+        offsets
+    }
+
+    // number of lattice sites in box with size `box_size` (in unit of lattice cells).
+    pub fn sites(&self, (box_x, box_y, box_z): (usize, usize, usize)) -> usize {
+        self.atoms_per_cell() * box_x * box_y * box_z
+    }
+
+    // the range of lattice coordinate in box, lattice coordinates in box are in [0, period) of each dimension.
+    pub fn periods(&self, (box_x, box_y, box_z): (usize, usize, usize)) -> [Inx; 3] {
+        let (box_x, box_y, box_z) = (box_x as Inx, box_y as Inx, box_z as Inx);
+        match self {
+            LatticeType::Bcc => [2 * box_x, box_y, box_z],
+            LatticeType::Fcc => [2 * box_x, 2 * box_y, 2 * box_z],
+        }
+    }
+
+    /**
+     * wrap lattice coordinate into box in the periodic dimensions.
+     * `None` is returned if the coordinate is out of box in a free (non-periodic) dimension.
+     * The periods are even for FCC lattice, thus, the parity of coordinate sum is kept after wrapping.
+     */
+    pub fn wrap(&self, coord: (Inx, Inx, Inx), box_size: (usize, usize, usize), periodic: [bool; 3]) -> Option<(Inx, Inx, Inx)> {
+        let periods = self.periods(box_size);
+        let mut coord = [coord.0, coord.1, coord.2];
+        for d in 0..3 {
+            if periodic[d] {
+                coord[d] = coord[d].rem_euclid(periods[d]);
+            } else if coord[d] < 0 || coord[d] >= periods[d] {
+                return None;
+            }
+        }
+        Some((coord[0], coord[1], coord[2]))
+    }
+
+    /**
+     * 1D lattice index of a lattice coordinate in box with size `box_size` (in unit of lattice cells).
+     * The indexes of all lattice sites in box are continuous in range [0, atoms_per_cell * box_x * box_y * box_z).
+     */
+    pub fn index(&self, (x, y, z): (Inx, Inx, Inx), (box_x, box_y, _): (usize, usize, usize)) -> Inx {
+        let (box_x, box_y) = (box_x as Inx, box_y as Inx);
+        match self {
+            // z * 2 * x_size * y_size  + y * 2 * x_size  + x;
+            LatticeType::Bcc => 2 * box_x * (z * box_y + y) + x,
+            // x is halved, as there is only one site for each 2 coordinates along x.
+            LatticeType::Fcc => (z * 2 * box_y + y) * box_x + x.div_euclid(2),
+        }
+    }
+
+    // lattice coordinate of 1D lattice index, it is the inverse of `index`.
+    pub fn coordinate(&self, index: Inx, (box_x, box_y, _): (usize, usize, usize)) -> (Inx, Inx, Inx) {
+        let (box_x, box_y) = (box_x as Inx, box_y as Inx);
+        match self {
+            LatticeType::Bcc => {
+                let z = index / (2 * box_x * box_y);
+                let left = index % (2 * box_x * box_y);
+                (left % (2 * box_x), left / (2 * box_x), z)
+            }
+            LatticeType::Fcc => {
+                let row = index / box_x;
+                let (y, z) = (row % (2 * box_y), row / (2 * box_y));
+                // x has the same parity as y + z.
+                let x = 2 * (index % box_x) + (y + z) % 2;
+                (x, y, z)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fcc_index() {
+        let box_size = (3, 2, 4);
+        let lattice = LatticeType::Fcc;
+        let mut indexes = Vec::new();
+        for z in 0..8 {
+            for y in 0..4 {
+                for x in 0..6 {
+                    if (x + y + z) % 2 == 0 {
+                        let index = lattice.index((x, y, z), box_size);
+                        assert_eq!(lattice.coordinate(index, box_size), (x, y, z));
+                        indexes.push(index);
+                    }
+                }
+            }
+        }
+        // indexes are continuous.
+        assert_eq!(indexes, (0..(4 * 3 * 2 * 4)).collect::<Vec<Inx>>());
+    }
+
+    #[test]
+    fn test_bcc_index() {
+        let box_size = (3, 2, 4);
+        let lattice = LatticeType::Bcc;
+        assert_eq!(lattice.index((5, 1, 2), box_size), 5 + 6 + 24);
+        assert_eq!(lattice.coordinate(35, box_size), (5, 1, 2));
+    }
+
+    #[test]
+    fn test_site_position() {
+        let a = 3.0;
+        for lattice in [LatticeType::Bcc, LatticeType::Fcc].iter() {
+            for &coord in [(0, 0, 0), (3, 1, 2), (4, 2, 2), (-1, 0, 1)].iter() {
+                let (x, y, z) = lattice.site_position(coord, a);
+                assert_eq!(lattice.nearest_site(x + 0.01, y - 0.01, z + 0.02, a), coord);
+            }
+        }
+        assert_eq!(LatticeType::Bcc.site_position((3, 1, 2), a), (4.5, 4.5, 7.5));
+    }
+
+    #[test]
+    fn test_neighbor_offsets() {
+        assert_eq!(LatticeType::Bcc.neighbor_offsets(1).len(), 8);
+        assert_eq!(LatticeType::Bcc.neighbor_offsets(2).len(), 14);
+        assert_eq!(LatticeType::Bcc.neighbor_offsets(3).len(), 26);
+        assert_eq!(LatticeType::Fcc.neighbor_offsets(1).len(), 12);
+        assert_eq!(LatticeType::Fcc.neighbor_offsets(2).len(), 18);
+        assert_eq!(LatticeType::Fcc.neighbor_offsets(3).len(), 42);
+
+        for &coord in [(3, 1, 2), (4, 0, 1)].iter() {
+            let half = LatticeType::Bcc.half_coordinate(coord);
+            assert_eq!(LatticeType::Bcc.lattice_coordinate(half), coord);
+        }
+    }
+
+    #[test]
+    fn test_wrap() {
+        let box_size = (3, 2, 4);
+        let periodic = [true, true, false];
+        assert_eq!(LatticeType::Bcc.wrap((-1, 2, 3), box_size, periodic), Some((5, 0, 3)));
+        assert_eq!(LatticeType::Bcc.wrap((7, -3, 3), box_size, periodic), Some((1, 1, 3)));
+        assert_eq!(LatticeType::Bcc.wrap((0, 0, 4), box_size, periodic), None);
+        assert_eq!(LatticeType::Fcc.wrap((-1, 4, 7), box_size, periodic), Some((5, 0, 7)));
+        assert_eq!(LatticeType::Fcc.wrap((0, 0, -2), box_size, periodic), None);
+    }
+}
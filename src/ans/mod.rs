@@ -1,6 +1,16 @@
 pub(crate) mod voronoy;
+pub(crate) mod ans_atom;
 pub(crate) mod analysis;
 pub(crate) mod box_config;
+pub(crate) mod lattice;
+pub(crate) mod ws_result;
+pub(crate) mod cluster;
+pub(crate) mod dumbbell;
+pub(crate) mod time_series;
+pub(crate) mod neighbor;
+pub(crate) mod per_atom;
+pub(crate) mod cna;
+pub(crate) mod csp;
 #[cfg(feature = "minio-analysis")]
 mod libminio_rw;
 mod minio_input;
@@ -0,0 +1,187 @@
+// Neighbor search of atoms by cell list, in a periodic (or free) simulation box.
+
+use rayon::prelude::*;
+
+use crate::xyz::sim_box::SimBox;
+
+// max cells number in each dimension, to limit the memory of cell list.
+const MAX_CELLS: usize = 128;
+// min expected number of atoms in the neighbor search sphere.
+const SEARCH_ATOMS: f64 = 40.0;
+
+/**
+ * Atoms are sorted into cells (in fractional coordinate) whose width is not less than the cutoff,
+ * then neighbors of an atom are searched in its cell and the surrounding cells.
+ * In periodic directions, the images of atoms are also searched, even if the box is smaller than the cutoff.
+ */
+pub struct CellList {
+    sim_box: SimBox,
+    cutoff: f64,
+    // cells number in each dimension.
+    cells: [usize; 3],
+    // number of surrounding cells to search in each dimension.
+    ranges: [i64; 3],
+    // fractional coordinates of atoms, wrapped into box in periodic directions.
+    fractional: Vec<[f64; 3]>,
+    // cell index of each atom.
+    atom_cells: Vec<[i64; 3]>,
+    // atoms in each cell.
+    cell_atoms: Vec<Vec<usize>>,
+}
+
+impl CellList {
+    pub fn new(positions: &[[f64; 3]], sim_box: SimBox, cutoff: f64) -> CellList {
+        let heights = sim_box.heights();
+        let mut cells = [1; 3];
+        let mut ranges = [1; 3];
+        for d in 0..3 {
+            cells[d] = ((heights[d] / cutoff).floor() as usize).clamp(1, MAX_CELLS);
+            ranges[d] = (cutoff * cells[d] as f64 / heights[d]).ceil() as i64;
+        }
+
+        let mut cell_atoms = vec![Vec::new(); cells[0] * cells[1] * cells[2]];
+        let mut fractional = Vec::with_capacity(positions.len());
+        let mut atom_cells = Vec::with_capacity(positions.len());
+        for (i, r) in positions.iter().enumerate() {
+            let mut f = sim_box.fractional(*r);
+            let mut cell = [0; 3];
+            for d in 0..3 {
+                if sim_box.pbc[d] {
+                    f[d] -= f[d].floor();
+                }
+                // atoms out of box in free directions are put into the boundary cells.
+                cell[d] = ((f[d] * cells[d] as f64).floor() as i64).clamp(0, cells[d] as i64 - 1);
+            }
+            cell_atoms[(cell[2] as usize * cells[1] + cell[1] as usize) * cells[0] + cell[0] as usize].push(i);
+            fractional.push(f);
+            atom_cells.push(cell);
+        }
+        CellList { sim_box, cutoff, cells, ranges, fractional, atom_cells, cell_atoms }
+    }
+
+    // neighbors of atom `i` within cutoff: the neighbor atom index and the vector from atom `i` to the neighbor.
+    pub fn neighbors(&self, i: usize) -> Vec<(usize, [f64; 3])> {
+        let cutoff2 = self.cutoff * self.cutoff;
+        let mut neighbors = Vec::new();
+        let center = self.atom_cells[i];
+        for dz in -self.ranges[2]..=self.ranges[2] {
+            for dy in -self.ranges[1]..=self.ranges[1] {
+                for dx in -self.ranges[0]..=self.ranges[0] {
+                    let offset = [dx, dy, dz];
+                    // cell index (wrapped into box) and image shift (in fractional coordinate).
+                    let mut cell = [0; 3];
+                    let mut shift = [0.0; 3];
+                    let mut in_box = true;
+                    for d in 0..3 {
+                        let c = center[d] + offset[d];
+                        let n = self.cells[d] as i64;
+                        if self.sim_box.pbc[d] {
+                            cell[d] = c.rem_euclid(n) as usize;
+                            shift[d] = c.div_euclid(n) as f64;
+                        } else if c >= 0 && c < n {
+                            cell[d] = c as usize;
+                        } else {
+                            in_box = false;
+                        }
+                    }
+                    if !in_box {
+                        continue;
+                    }
+                    let self_image = shift == [0.0; 3];
+                    for &j in &self.cell_atoms[(cell[2] * self.cells[1] + cell[1]) * self.cells[0] + cell[0]] {
+                        if self_image && j == i {
+                            continue;
+                        }
+                        let (fi, fj) = (self.fractional[i], self.fractional[j]);
+                        let delta = self.sim_box.cartesian([fj[0] + shift[0] - fi[0], fj[1] + shift[1] - fi[1],
+                            fj[2] + shift[2] - fi[2]]);
+                        if delta[0] * delta[0] + delta[1] * delta[1] + delta[2] * delta[2] <= cutoff2 {
+                            neighbors.push((j, delta));
+                        }
+                    }
+                }
+            }
+        }
+        neighbors
+    }
+}
+
+// the search radius is doubled for atoms without enough neighbors, for at most this times.
+const MAX_SEARCH_RETRIES: usize = 3;
+
+/**
+ * find `n` nearest neighbors of each atom: vectors from the atom to its neighbors, sorted by distance.
+ * The neighbors are searched in a sphere with about max(40, 3n) atoms in average, and the sphere is enlarged
+ * for atoms in low density regions (e.g. surface atoms). Isolated atoms may still have fewer neighbors.
+ * If `sim_box` is `None`, atoms are in a box with free boundaries covering all atoms.
+ */
+pub fn nearest_neighbors(positions: &[[f64; 3]], sim_box: Option<SimBox>, n: usize) -> Vec<Vec<[f64; 3]>> {
+    let sim_box = match sim_box.or_else(|| bounding_box(positions)) {
+        Some(sim_box) if !positions.is_empty() => sim_box,
+        _ => return vec![Vec::new(); positions.len()],
+    };
+    let search_atoms = SEARCH_ATOMS.max(3.0 * n as f64);
+    let mut cutoff = (3.0 * search_atoms * sim_box.volume() / (4.0 * std::f64::consts::PI * positions.len() as f64)).cbrt();
+
+    let mut nearest: Vec<Vec<[f64; 3]>> = vec![Vec::new(); positions.len()];
+    let mut pending: Vec<usize> = (0..positions.len()).collect();
+    for retry in 0..=MAX_SEARCH_RETRIES {
+        let cell_list = CellList::new(positions, sim_box, cutoff);
+        let found: Vec<Vec<[f64; 3]>> = pending.par_iter().map(|&i| {
+            let mut neighbors: Vec<(f64, [f64; 3])> = cell_list.neighbors(i).into_iter()
+                .map(|(_, d)| (d[0] * d[0] + d[1] * d[1] + d[2] * d[2], d))
+                .collect();
+            neighbors.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            neighbors.iter().take(n).map(|(_, d)| *d).collect()
+        }).collect();
+        let mut short = Vec::new();
+        for (i, neighbors) in pending.into_iter().zip(found) {
+            if neighbors.len() < n && retry < MAX_SEARCH_RETRIES {
+                short.push(i);
+            } else {
+                nearest[i] = neighbors;
+            }
+        }
+        if short.is_empty() {
+            break;
+        }
+        pending = short;
+        cutoff *= 2.0;
+    }
+    nearest
+}
+
+// an orthogonal box with free boundaries covering all atoms, used when the simulation box is unknown.
+pub fn bounding_box(positions: &[[f64; 3]]) -> Option<SimBox> {
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for r in positions {
+        for d in 0..3 {
+            min[d] = min[d].min(r[d]);
+            max[d] = max[d].max(r[d]);
+        }
+    }
+    let length = |d: usize| (max[d] - min[d]).max(1.0);
+    SimBox::new([[length(0), 0.0, 0.0], [0.0, length(1), 0.0], [0.0, 0.0, length(2)]], min, [false; 3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbors_in_small_box() {
+        // BCC unit cell: 8 neighbors (images of the body center) at sqrt(3)/2, and 6 images of itself at 1.0.
+        let sim_box = SimBox::orthogonal((1.0, 1.0, 1.0), [0.0; 3]).unwrap();
+        let cell_list = CellList::new(&[[0.0, 0.0, 0.0], [0.5, 0.5, 0.5]], sim_box, 1.1);
+        let neighbors = cell_list.neighbors(0);
+        assert_eq!(neighbors.len(), 14);
+        assert_eq!(neighbors.iter().filter(|(j, _)| *j == 1).count(), 8);
+        assert!(neighbors.iter().all(|(_, d)| (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]) > 0.7));
+
+        // free boundaries: no images.
+        let sim_box = bounding_box(&[[0.0, 0.0, 0.0], [0.5, 0.5, 0.5]]).unwrap();
+        let cell_list = CellList::new(&[[0.0, 0.0, 0.0], [0.5, 0.5, 0.5]], sim_box, 1.1);
+        assert_eq!(cell_list.neighbors(1).len(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+// Per-atom analyses (e.g. CNA and CSP) on the first frame of xyz or binary files.
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::ans::ans_atom::{xyz_frame, AnsAtom};
+use crate::cli;
+use crate::conv::binary_parser;
+use crate::json;
+use crate::xyz::sim_box::SimBox;
+use crate::xyz::particle::ParticleLine;
+use crate::xyz::xyz_reader::Reader;
+
+pub trait PerAtomAnalysis {
+    // analyse atoms in the simulation box (`None` for free boundaries), and write the result to `output`.
+    fn analyse<A: AnsAtom>(&self, atoms: &[A], sim_box: Option<SimBox>, output: &str) -> Result<(), String>;
+}
+
+/**
+ * run per-atom analysis on the first frame of xyz file or binary file (`binary` is not `None`).
+ * The simulation box is read from the `Lattice=` header of extxyz file (the directions are periodic if they are
+ * periodic in both the header and `periodic`), otherwise, free boundaries are used.
+ */
+pub fn per_atom_wrapper<P: PerAtomAnalysis>(analysis: &P, input: &str, output: &str, binary: Option<(cli::FormatStandard, u32)>,
+                                            periodic: [bool; 3], verbose: bool) -> Result<(), String> {
+    match binary {
+        Some((standard, ranks)) => {
+            let mut reader = binary_parser::make_frame_reader(standard, input, ranks)
+                .map_err(|e| format!("read input binary file {} error: {}", input, e))?;
+            let atoms = reader.next().ok_or_else(|| format!("no frame in input binary file {}", input))?;
+            if verbose {
+                println!("atom size is {}", atoms.len());
+            }
+            println!("Warning: simulation box is not saved in binary files, free boundaries are used.");
+            analysis.analyse(&atoms, None, output)
+        }
+        None => {
+            let file = File::open(input).map_err(|e| format!("open file {} error: {}", input, e))?;
+            let snapshot = Reader::new(file).read_snapshot::<ParticleLine>()
+                .map_err(|e| format!("read input xyz file error: {:?}", e))?;
+            if verbose {
+                println!("atom size is {}", snapshot.size());
+            }
+            let (atoms, sim_box) = xyz_frame(&snapshot).map_err(|e| format!("read input xyz file error: {}", e))?;
+            let sim_box = sim_box.map(|mut sim_box| {
+                for (pbc, &p) in sim_box.pbc.iter_mut().zip(periodic.iter()) {
+                    *pbc = *pbc && p;
+                }
+                sim_box
+            });
+            if sim_box.is_none() {
+                println!("Warning: simulation box is not found in input file, free boundaries are used.");
+            }
+            analysis.analyse(&atoms, sim_box, output)
+        }
+    }
+}
+
+pub fn positions<A: AnsAtom>(atoms: &[A]) -> Vec<[f64; 3]> {
+    atoms.iter().map(|atom| {
+        let (x, y, z) = atom.position();
+        [x as f64, y as f64, z as f64]
+    }).collect()
+}
+
+// write the per-atom result to `output` as csv table, and the summary to the json summary file.
+pub fn write_files<F, G>(output: &str, write_csv: F, write_summary: G) -> Result<(), String>
+    where F: FnOnce(&mut BufWriter<File>) -> io::Result<()>, G: FnOnce(&mut BufWriter<File>) -> io::Result<()> {
+    let path = Path::new(output);
+    write_file(path, write_csv)?;
+    write_file(&json::summary_path(path), write_summary)
+}
+
+fn write_file<F>(path: &Path, write: F) -> Result<(), String> where F: FnOnce(&mut BufWriter<File>) -> io::Result<()> {
+    let file = File::create(path).map_err(|e| format!("create file {} error: {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer).and_then(|_| writer.flush()).map_err(|e| format!("write file {} error: {}", path.display(), e))
+}
+
+// write atoms with a value column `column`.
+pub fn write_csv<W: Write, A: AnsAtom, T: Display>(writer: &mut W, atoms: &[A], column: &str, values: &[T]) -> io::Result<()> {
+    writeln!(writer, "atom_index,atom_id,element,position:x,position:y,position:z,{}", column)?;
+    for (i, (atom, value)) in atoms.iter().zip(values).enumerate() {
+        let (x, y, z) = atom.position();
+        writeln!(writer, "{},{},{},{},{},{},{}", i, atom.id().map_or(String::new(), |id| id.to_string()),
+                 atom.element(), x, y, z, value)?;
+    }
+    Ok(())
+}
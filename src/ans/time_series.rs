@@ -0,0 +1,184 @@
+// Cascade time-series analysis: Wigner-Seitz analysis over all frames of a trajectory.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::ans::ans_atom::{xyz_frame, AnsAtom};
+use crate::ans::box_config::{BoxConfig, config_simulation_box};
+use crate::ans::cluster;
+use crate::ans::voronoy;
+use crate::cli;
+use crate::conv::binary_parser;
+use crate::json;
+use crate::xyz::sim_box::SimBox;
+use crate::xyz::particle::ParticleLine;
+use crate::xyz::xyz_reader::Reader;
+
+const CSV_HEADER: &str = "frame,step,time,vacancies,interstitials,frenkel_pairs,clusters\n";
+
+// defects in a frame.
+pub struct FrameDefects {
+    pub frame: usize,
+    // step and time in frame header, they are only available for v2 binary files.
+    pub step: Option<u64>,
+    pub time: Option<f64>,
+    pub vacancies: usize,
+    pub interstitials: usize,
+    pub frenkel_pairs: usize,
+    // clusters number, if clustering is enabled.
+    pub clusters: Option<usize>,
+}
+
+impl FrameDefects {
+    pub fn defects(&self) -> usize {
+        self.vacancies + self.interstitials
+    }
+}
+
+// a frame of trajectory: atoms and the simulation box in the frame, with step and time.
+type Frame<A> = (Vec<A>, Option<SimBox>, Option<u64>, Option<f64>);
+
+/**
+ * run Wigner-Seitz analysis over all frames of a multi-frame xyz file or binary file (`binary` is not `None`),
+ * and write the defects of each frame to `output` as csv table, and the peak and surviving defects to the json summary file.
+ * The simulation box is configured by the first frame.
+ */
+pub fn time_series_wrapper(input: &str, output: &str, binary: Option<(cli::FormatStandard, u32)>,
+                           box_config: &mut BoxConfig, verbose: bool) -> Result<(), String> {
+    let series = match binary {
+        Some((standard, ranks)) => {
+            let mut reader = binary_parser::make_frame_reader(standard, input, ranks)
+                .map_err(|e| format!("read input binary file {} error: {}", input, e))?;
+            let mut frame: u32 = 0;
+            let frames = std::iter::from_fn(move || {
+                let header = reader.frame_header(frame);
+                let atoms = reader.next()?;
+                frame += 1;
+                Some(Ok((atoms, None, header.map(|h| h.step), header.map(|h| h.time))))
+            });
+            analyse_frames(frames, box_config, verbose)?
+        }
+        None => {
+            let file = File::open(input).map_err(|e| format!("open file {} error: {}", input, e))?;
+            // columns of extxyz files are mapped by `Properties=` in comment line.
+            let frames = Reader::new(file).into_frames::<ParticleLine>().enumerate().map(|(frame, snapshot)| {
+                snapshot.map_err(|e| e.to_string())
+                    .and_then(|snapshot| xyz_frame(&snapshot))
+                    .map(|(atoms, sim_box)| (atoms, sim_box, None, None))
+                    .map_err(|e| format!("read input xyz file error at frame {}: {}", frame, e))
+            });
+            analyse_frames(frames, box_config, verbose)?
+        }
+    };
+
+    let path = Path::new(output);
+    let write = |path: &Path, json: bool| -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if json {
+            write_json_summary(&mut writer, &series)?;
+        } else {
+            write_csv(&mut writer, &series)?;
+        }
+        writer.flush()
+    };
+    write(path, false).map_err(|e| format!("write file {} error: {}", path.display(), e))?;
+    let summary_path = json::summary_path(path);
+    write(&summary_path, true).map_err(|e| format!("write file {} error: {}", summary_path.display(), e))?;
+    Ok(())
+}
+
+fn analyse_frames<A, I>(frames: I, box_config: &mut BoxConfig, verbose: bool) -> Result<Vec<FrameDefects>, String>
+    where A: AnsAtom, I: Iterator<Item=Result<Frame<A>, String>> {
+    let mut series: Vec<FrameDefects> = Vec::new();
+    for (frame, item) in frames.enumerate() {
+        let (atoms, sim_box, step, time) = item?;
+        if frame == 0 && !config_simulation_box(&atoms, sim_box.as_ref(), box_config, verbose) {
+            return Err(String::from("config simulation box failed"));
+        }
+        let result = voronoy::do_analysis(box_config, &atoms);
+        let clusters = box_config.cluster_shell.map(|shell| cluster::find_clusters(&result, box_config, shell).len());
+        let defects = FrameDefects {
+            frame,
+            step,
+            time,
+            vacancies: result.vacancies.len(),
+            interstitials: result.interstitials.len(),
+            frenkel_pairs: result.frenkel_pairs(),
+            clusters,
+        };
+        if verbose {
+            println!("frame {}: vacancies: {}, interstitials: {}, Frenkel pairs: {}",
+                     frame, defects.vacancies, defects.interstitials, defects.frenkel_pairs);
+        }
+        series.push(defects);
+    }
+    Ok(series)
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or(String::new(), |v| v.to_string())
+}
+
+pub fn write_csv<W: Write>(writer: &mut W, series: &[FrameDefects]) -> io::Result<()> {
+    writer.write_all(CSV_HEADER.as_bytes())?;
+    for f in series {
+        writeln!(writer, "{},{},{},{},{},{},{}", f.frame, optional(f.step), optional(f.time),
+                 f.vacancies, f.interstitials, f.frenkel_pairs, optional(f.clusters))?;
+    }
+    Ok(())
+}
+
+// summary of time series: the peak defects and the surviving defects (defects in the last frame).
+pub fn write_json_summary<W: Write>(writer: &mut W, series: &[FrameDefects]) -> io::Result<()> {
+    let mut items = vec![("frames", series.len().to_string())];
+    // the first frame with max defects.
+    let peak = series.iter().fold(None, |peak: Option<&FrameDefects>, f| match peak {
+        Some(p) if p.defects() >= f.defects() => Some(p),
+        _ => Some(f),
+    });
+    if let Some(peak) = peak {
+        items.push(("peak_frame", peak.frame.to_string()));
+        items.push(("peak_step", json::optional(peak.step.map(|s| s.to_string()))));
+        items.push(("peak_defects", peak.defects().to_string()));
+        items.push(("peak_frenkel_pairs", peak.frenkel_pairs.to_string()));
+    }
+    if let Some(last) = series.last() {
+        items.push(("surviving_vacancies", last.vacancies.to_string()));
+        items.push(("surviving_interstitials", last.interstitials.to_string()));
+        items.push(("surviving_frenkel_pairs", last.frenkel_pairs.to_string()));
+    }
+    writeln!(writer, "{}", json::pretty_object(items, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_frame(frame: usize, vacancies: usize) -> FrameDefects {
+        FrameDefects {
+            frame,
+            step: Some(frame as u64 * 100),
+            time: Some(frame as f64 * 0.1),
+            vacancies,
+            interstitials: vacancies,
+            frenkel_pairs: vacancies,
+            clusters: None,
+        }
+    }
+
+    #[test]
+    fn test_write_time_series() {
+        let series = vec![make_frame(0, 0), make_frame(1, 30), make_frame(2, 12), make_frame(3, 4)];
+        let mut out: Vec<u8> = Vec::new();
+        write_csv(&mut out, &series).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\n1,100,0.1,30,30,30,\n"));
+
+        let mut out: Vec<u8> = Vec::new();
+        write_json_summary(&mut out, &series).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\"peak_step\": 100,\n  \"peak_defects\": 60,\n"));
+        assert!(out.contains("\"surviving_frenkel_pairs\": 4\n"));
+    }
+}
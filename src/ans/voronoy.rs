@@ -1,16 +1,24 @@
 use std::{f32};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
 use rayon::prelude::*;
-use xyzio::Atom;
 
-use crate::xyz::xyz_reader;
+use crate::ans::ans_atom::AnsAtom;
+use crate::ans::box_config::BoxConfig;
+use crate::ans::cluster;
+use crate::ans::dumbbell::{self, Dumbbell};
+use crate::ans::ws_result::{self, Interstitial, Site, WsResult};
+use crate::json;
 
 pub type Float = f32;
-type Inx = i32;
+pub type Inx = i32;
 
+// default lattice constant of BCC lattice (Fe).
 pub const LATTICE_CONST: Float = 2.85532;
+// default lattice constant of FCC lattice (Cu).
+pub const FCC_LATTICE_CONST: Float = 3.615;
 
 /**
  * The coordinate offset of 8 minor lattices surrounding a major lattice.
@@ -61,107 +69,150 @@ const NORMAL_VECTOR: [(Float, Float, Float); 8] = [
   */
 const D: Float = -3.0 / 4.0;
 
-const ANALYSIS_OUT_FILE_HEADER: &str = "type, lattice:x, lattice;y, lattice:z,\
- position:x, position:y, position:z\n\
-";
+/**
+ * do analysis and write the vacancies and interstitials to `output` in csv format,
+ * and the defect summary to the json summary file.
+ * If clustering is enabled, the clusters are written to a csv file with extension `.clusters.csv`.
+ */
+pub fn do_analysis_wrapper<A: AnsAtom>(output: &str, box_config: &BoxConfig, atoms: &[A]) {
+    let result = do_analysis(box_config, atoms);
+    if result.skipped_atoms != 0 {
+        println!("Warning: {} atom(s) out of box in free boundaries are skipped.", result.skipped_atoms);
+    }
+    println!("vacancies: {}, interstitials: {}, Frenkel pairs: {}",
+             result.vacancies.len(), result.interstitials.len(), result.frenkel_pairs());
+    let clusters = box_config.cluster_shell.map(|shell| cluster::find_clusters(&result, box_config, shell));
+    let orientations = dumbbell::orientation_histogram(&result.interstitials);
+    if !orientations.is_empty() {
+        let orientations: Vec<String> = orientations.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+        println!("dumbbell orientations: {}", orientations.join(", "));
+    }
+    if let Some(clusters) = &clusters {
+        println!("clusters: {}, size distribution: {:?}", clusters.len(), cluster::size_distribution(clusters));
+    }
 
-pub fn do_analysis_wrapper(output: &str, box_size: (usize, usize, usize), box_start: (Float, Float, Float), snapshot: &xyz_reader::Snapshot<Atom>) {
-    // prepare file
     let path = Path::new(output);
+    write_file(path, |writer| ws_result::write_csv(writer, &result));
+    write_file(&json::summary_path(path),
+               |writer| ws_result::write_json_summary(writer, &result, clusters.as_deref(), box_config));
+    if let Some(clusters) = &clusters {
+        write_file(&path.with_extension("clusters.csv"), |writer| cluster::write_csv(writer, clusters));
+    }
+}
 
+fn write_file<F>(path: &Path, write: F) where F: FnOnce(&mut BufWriter<File>) -> std::io::Result<()> {
     // Open a file in write-only mode, returns `io::Result<File>`
-    let file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", path.display(), why.to_string()),
+    let file = match File::create(path) {
+        Err(why) => panic!("couldn't create {}: {}", path.display(), why),
         Ok(file) => file,
     };
     let mut writer = BufWriter::new(file);
-    writer.write(ANALYSIS_OUT_FILE_HEADER.as_bytes()).unwrap();
-    do_analysis(&mut writer, box_size, box_start, &snapshot.atoms);
+    write(&mut writer).unwrap();
     writer.flush().unwrap();
 }
 
 // in do_analysis, calculate atom's occupation by box size and its lattice index,
-// then atoms with >= 2 occupation will be logged.
-fn do_analysis(writer: &mut BufWriter<File>, (box_x_size, box_y_size, _): (usize, usize, usize), (box_x_start, box_y_start, box_z_start): (Float, Float, Float), atoms: &Vec<xyzio::Atom>) {
+// then empty lattices are vacancies, and the extra atoms in lattices with >= 2 occupation are interstitials.
+// For lattices occupied by exactly 2 atoms, the vector between the two atoms gives the dumbbell orientation.
+pub fn do_analysis<A: AnsAtom>(box_config: &BoxConfig, atoms: &[A]) -> WsResult {
     let atoms_size = atoms.len();
+    let lattice = box_config.lattice;
+    let lattice_const = box_config.lattice_const;
+    let box_size = box_config.box_size_;
+    let (box_x_start, box_y_start, box_z_start) = box_config.box_start;
 
     // calculate global index for each atom in parallel.
-    // variable `global_atom_indexes` saves the lattice index of each atom.
-    let mut global_atom_indexes: Vec<(Inx, usize)> = (0..atoms_size).into_par_iter().map(|i| {
+    // variable `global_atom_indexes` saves the lattice index of each atom, its squared distance to the lattice
+    // and its displacement from the lattice.
+    let mut global_atom_indexes: Vec<_> = (0..atoms_size).into_par_iter().filter_map(|i| {
         // scale to lattice const unit.
         // calculate lattice index of each atom
-        // note: x is doubled.
-        let (x, y, z) = voronoy(atoms[i].x - box_x_start, atoms[i].y - box_y_start, atoms[i].z - box_z_start);
-        // todo mode box_x/y/z_size if index is large then box size (todo for user specified box size, not auto size).
-        // todo or mode box_x/y/z_size if real box coord not starting from 0.
-        // which also means: make x,y,z belongs [0, box_x/y/z_size).
-        // z * 2 * x_size * y_size  + y * 2 * x_size  + x;
-        (2 * (box_x_size as Inx) * (z * box_y_size as Inx + y) + x, i)
+        // note: for BCC, x is doubled.
+        let (x, y, z) = atoms[i].position();
+        let (x, y, z) = (x - box_x_start, y - box_y_start, z - box_z_start);
+        let coord = lattice.nearest_site(x, y, z, lattice_const);
+        let (site_x, site_y, site_z) = lattice.site_position(coord, lattice_const);
+        let displacement = (x - site_x, y - site_y, z - site_z);
+        let dist2 = displacement.0.powi(2) + displacement.1.powi(2) + displacement.2.powi(2);
+        // make x,y,z belongs to the box in periodic dimensions,
+        // atoms out of box in free dimensions are skipped.
+        let coord = lattice.wrap(coord, box_size, box_config.periodic)?;
+        Some((lattice.index(coord, box_size), i, dist2, displacement))
     }).collect();
 
-    // sort in parallel
-    global_atom_indexes.par_sort_by(|a, b| a.0.cmp(&b.0));
-
-    // first pre atom metadata with the same lattice index.
-    // each item in this tuple: lattice index, atom array index
-    // and draft(means this lattice is not written to file before).
-    let mut pre_global_atom_index_data: (Inx, usize, bool) = (-1, 0, false);
-
-    // iterate all lattice point to search vacancies and interstitials
-    for (lat_index, atom_index) in global_atom_indexes {
-        if lat_index == pre_global_atom_index_data.0 {
-            // more than one atoms occupy one lattice, log it.
-            write_line(writer, lat_index, &atoms[atom_index], (box_x_size, box_y_size));
-            if pre_global_atom_index_data.2 {
-                // write the first atom with the same lattice id
-                write_line(writer, pre_global_atom_index_data.0, &atoms[pre_global_atom_index_data.1], (box_x_size, box_y_size));
-            }
-            pre_global_atom_index_data.2 = false
-        } else if lat_index == pre_global_atom_index_data.0 + 1 {
-            // it is a new lattice
-            pre_global_atom_index_data = (lat_index, atom_index, true);
+    // sort in parallel, atoms in the same lattice are sorted by the distance to the lattice.
+    global_atom_indexes.par_sort_by(|a, b| a.0.cmp(&b.0).then(a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal)));
+
+    let sites = lattice.sites(box_size);
+    let mut result = WsResult {
+        atoms: atoms_size,
+        sites,
+        skipped_atoms: atoms_size - global_atom_indexes.len(),
+        vacancies: Vec::new(),
+        interstitials: Vec::new(),
+        occupancy: BTreeMap::new(),
+    };
+    let make_site = |index: Inx| {
+        let coord = lattice.coordinate(index, box_size);
+        let (x, y, z) = lattice.site_position(coord, lattice_const);
+        Site { index, coord, position: (x + box_x_start, y + box_y_start, z + box_z_start) }
+    };
+
+    // iterate all lattice point to search vacancies and interstitials.
+    // `next_index` is the lattice index next to the last visited lattice.
+    let mut next_index: Inx = 0;
+    let mut i = 0;
+    while i < global_atom_indexes.len() {
+        let lat_index = global_atom_indexes[i].0;
+        // skipped lattices are vacancies.
+        result.vacancies.extend((next_index..lat_index).map(make_site));
+        // atoms in the same lattice, the first one (nearest to the lattice) occupies the lattice.
+        let mut j = i + 1;
+        while j < global_atom_indexes.len() && global_atom_indexes[j].0 == lat_index {
+            j += 1;
+        }
+        // the vector between displacements is not affected by periodic boundaries.
+        let dumbbell = if j - i == 2 {
+            let (d1, d2) = (global_atom_indexes[i].3, global_atom_indexes[i + 1].3);
+            Some(Dumbbell::new((d2.0 - d1.0, d2.1 - d1.1, d2.2 - d1.2)))
         } else {
-            // It is also a new lattice, but it must skip something
-            // (skipped some lattices, log them as vacancy).
-            for k in (pre_global_atom_index_data.0 + 1)..lat_index {
-                let atom = xyzio::Atom {
-                    element: "V".to_string(),
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                };
-                write_line(writer, k, &atom, (box_x_size, box_y_size))
-            }
-            pre_global_atom_index_data = (lat_index, atom_index, true);
+            None
+        };
+        for &(_, atom_index, _, _) in &global_atom_indexes[(i + 1)..j] {
+            let atom = &atoms[atom_index];
+            result.interstitials.push(Interstitial {
+                site: make_site(lat_index),
+                atom_index,
+                atom_id: atom.id(),
+                element: atom.element().to_string(),
+                position: atom.position(),
+                dumbbell,
+            });
         }
+        *result.occupancy.entry(j - i).or_insert(0) += 1;
+        next_index = lat_index + 1;
+        i = j;
     }
-}
-
-// write a line to file by passing global lattice index and atom information
-fn write_line(writer: &mut BufWriter<File>, global_index: Inx, atom: &xyzio::Atom, (box_x_size, box_y_size): (usize, usize)) {
-    let (_box_size_x, _box_size_y) = (box_x_size as Inx, box_y_size as Inx);
-    let lat_z = global_index / (2 * _box_size_x * _box_size_y);
-    let lat_left = global_index % (2 * _box_size_x * _box_size_y);
-    let lat_y = lat_left / (2 * _box_size_x);
-    let lat_x = lat_left % (2 * _box_size_x);
-    writer.write(format!("{}, {}, {}, {}, {}, {}, {}\n",
-                         atom.element,
-                         lat_x, lat_y, lat_z,
-                         atom.x, atom.y, atom.z).as_bytes()).unwrap();
+    // the lattices after the last occupied lattice are also vacancies.
+    result.vacancies.extend((next_index..(sites as Inx)).map(make_site));
+    if !result.vacancies.is_empty() {
+        result.occupancy.insert(0, result.vacancies.len());
+    }
+    result
 }
 
 /**
  * calculate coordinate of nearest lattice for a atom:
- * X,Y,Z is the position of atom.
+ * X,Y,Z is the position of atom, and `lattice_const` is the lattice constant of BCC lattice.
  */
-pub fn voronoy(x: Float, y: Float, z: Float) -> (Inx, Inx, Inx) {
-    let lat_coord_x = (x / LATTICE_CONST).round() as Inx;
-    let mut lat_coord_y = (y / LATTICE_CONST).round() as Inx;
-    let mut lat_coord_z = (z / LATTICE_CONST).round() as Inx;
+pub fn voronoy(x: Float, y: Float, z: Float, lattice_const: Float) -> (Inx, Inx, Inx) {
+    let lat_coord_x = (x / lattice_const).round() as Inx;
+    let mut lat_coord_y = (y / lattice_const).round() as Inx;
+    let mut lat_coord_z = (z / lattice_const).round() as Inx;
 
-    let delta_x = x / LATTICE_CONST - (lat_coord_x as Float);
-    let delta_y = y / LATTICE_CONST - (lat_coord_y as Float);
-    let delta_z = z / LATTICE_CONST - (lat_coord_z as Float);
+    let delta_x = x / lattice_const - (lat_coord_x as Float);
+    let delta_y = y / lattice_const - (lat_coord_y as Float);
+    let delta_z = z / lattice_const - (lat_coord_z as Float);
 
     let flag_index = (if delta_z > 0.0 { 4 } else { 0 })
         | (if delta_y > 0.0 { 2 } else { 0 })
@@ -180,15 +231,79 @@ pub fn voronoy(x: Float, y: Float, z: Float) -> (Inx, Inx, Inx) {
     return (lat_coord_x, lat_coord_y, lat_coord_z);
 }
 
+/**
+ * calculate coordinate of nearest FCC lattice for a atom, the coordinate is in unit of half lattice constant.
+ * FCC lattice sites are the points with even coordinate sum in half lattice unit (D3 lattice),
+ * thus, the nearest site is found by rounding each coordinate, and if the sum of rounded coordinates is odd,
+ * rounding the coordinate with the largest rounding error to the other way.
+ */
+pub fn voronoy_fcc(x: Float, y: Float, z: Float, lattice_const: Float) -> (Inx, Inx, Inx) {
+    let half_lattice = lattice_const / 2.0;
+    let pos = [x / half_lattice, y / half_lattice, z / half_lattice];
+    let mut coord = [pos[0].round(), pos[1].round(), pos[2].round()];
+
+    if (coord[0] + coord[1] + coord[2]) as Inx % 2 != 0 {
+        let mut k = 0;
+        for d in 1..3 {
+            if (pos[d] - coord[d]).abs() > (pos[k] - coord[k]).abs() {
+                k = d;
+            }
+        }
+        coord[k] += if pos[k] > coord[k] { 1.0 } else { -1.0 };
+    }
+    (coord[0] as Inx, coord[1] as Inx, coord[2] as Inx)
+}
+
 
 #[cfg(test)]
 mod voronoy_tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
+    use xyzio::Atom;
+    use crate::ans::lattice::LatticeType;
 
     #[test]
     fn test_voronoy() {
-        assert_eq!(voronoy(1.377608, 1.501391, 1.471441), (1, 0, 0));
-        assert_eq!(voronoy(2.772588, 0.056315, -0.044443), (2, 0, 0));
+        assert_eq!(voronoy(1.377608, 1.501391, 1.471441, LATTICE_CONST), (1, 0, 0));
+        assert_eq!(voronoy(2.772588, 0.056315, -0.044443, LATTICE_CONST), (2, 0, 0));
+    }
+
+    #[test]
+    fn test_do_analysis() {
+        let box_config = BoxConfig::for_test(LatticeType::Bcc, (2, 2, 2), LATTICE_CONST);
+        let mut atoms: Vec<Atom> = (0..16).map(|i| {
+            let (x, y, z) = LatticeType::Bcc.site_position(LatticeType::Bcc.coordinate(i, (2, 2, 2)), LATTICE_CONST);
+            Atom { element: String::from("Fe"), x, y, z }
+        }).collect();
+        // move the atom of the last lattice to the lattice 0 (crossing periodic boundary).
+        atoms[15].x = -0.1;
+        atoms[15].y = 0.1;
+        atoms[15].z = 0.0;
+
+        let result = do_analysis(&box_config, &atoms);
+        assert_eq!(result.skipped_atoms, 0);
+        assert_eq!(result.vacancies.len(), 1);
+        assert_eq!(result.vacancies[0].index, 15);
+        assert_eq!(result.interstitials.len(), 1);
+        assert_eq!(result.interstitials[0].site.index, 0);
+        assert_eq!(result.interstitials[0].atom_index, 15);
+        assert_eq!(result.frenkel_pairs(), 1);
+        assert_eq!(result.occupancy.get(&1), Some(&14));
+        assert_eq!(result.occupancy.get(&2), Some(&1));
+        // the dumbbell of atom 0 at (0, 0, 0) and atom 15 at (-0.1, 0.1, 0.0).
+        let dumbbell = result.interstitials[0].dumbbell.unwrap();
+        assert_eq!(dumbbell.orientation, dumbbell::Orientation::D110);
+        assert!((dumbbell.vector.0 + 0.1).abs() < 1e-6 && (dumbbell.vector.1 - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_voronoy_fcc() {
+        let a = FCC_LATTICE_CONST;
+        assert_eq!(voronoy_fcc(0.1, 0.1, -0.1, a), (0, 0, 0));
+        // face center (0.5, 0.5, 0) with a small displacement.
+        assert_eq!(voronoy_fcc(0.52 * a, 0.45 * a, 0.05 * a, a), (1, 1, 0));
+        // (0.3, 0.3, 0.3) is nearer to face center (0.5, 0.5, 0) than corner (0, 0, 0) and (0.5, 0, 0.5).
+        assert_eq!(voronoy_fcc(0.32 * a, 0.31 * a, 0.3 * a, a), (1, 1, 0));
+        assert_eq!(voronoy_fcc(-0.48 * a, 0.02 * a, 0.51 * a, a), (-1, 0, 1));
     }
 }
@@ -0,0 +1,158 @@
+// Result of Wigner-Seitz defect analysis: vacancies, interstitials and the defect summary.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use crate::ans::box_config::BoxConfig;
+use crate::ans::cluster::{self, Cluster};
+use crate::ans::dumbbell::{self, Dumbbell};
+use crate::ans::lattice::LatticeType;
+use crate::ans::voronoy::{Float, Inx};
+use crate::json;
+
+const CSV_HEADER: &str = "defect,site_index,lattice:x,lattice:y,lattice:z,site:x,site:y,site:z,\
+atom_index,atom_id,element,position:x,position:y,position:z,\
+dumbbell:x,dumbbell:y,dumbbell:z,orientation\n";
+
+// a lattice site in the reference box.
+pub struct Site {
+    // 1D lattice index
+    pub index: Inx,
+    // lattice coordinate
+    pub coord: (Inx, Inx, Inx),
+    // ideal position of the site
+    pub position: (Float, Float, Float),
+}
+
+// an extra atom in a lattice site which is occupied by more than one atoms.
+pub struct Interstitial {
+    // the host site
+    pub site: Site,
+    // index of the atom in input file
+    pub atom_index: usize,
+    // atom id in input file, only available for binary files.
+    pub atom_id: Option<u64>,
+    pub element: String,
+    pub position: (Float, Float, Float),
+    // the dumbbell formed with the atom occupying the site, only for sites occupied by two atoms.
+    pub dumbbell: Option<Dumbbell>,
+}
+
+pub struct WsResult {
+    // atoms number in input file
+    pub atoms: usize,
+    // lattice sites number in the reference box
+    pub sites: usize,
+    // atoms out of box in free boundaries, they are not assigned to any site.
+    pub skipped_atoms: usize,
+    pub vacancies: Vec<Site>,
+    pub interstitials: Vec<Interstitial>,
+    // occupancy histogram: atoms number in a site -> sites number.
+    pub occupancy: BTreeMap<usize, usize>,
+}
+
+impl WsResult {
+    // a vacancy and an interstitial make a Frenkel pair.
+    pub fn frenkel_pairs(&self) -> usize {
+        std::cmp::min(self.vacancies.len(), self.interstitials.len())
+    }
+}
+
+// write vacancies and interstitials in csv format. For vacancies, the atom columns are empty.
+// The dumbbell columns are the dumbbell vector and orientation of interstitials in doubly occupied sites.
+pub fn write_csv<W: Write>(writer: &mut W, result: &WsResult) -> io::Result<()> {
+    writer.write_all(CSV_HEADER.as_bytes())?;
+    for site in &result.vacancies {
+        writeln!(writer, "vacancy,{},,,,,,,,,,", site_columns(site))?;
+    }
+    for inter in &result.interstitials {
+        let atom_id = inter.atom_id.map_or(String::new(), |id| id.to_string());
+        let dumbbell = inter.dumbbell.map_or(String::from(",,,"), |d| {
+            format!("{},{},{},{}", d.vector.0, d.vector.1, d.vector.2, d.orientation)
+        });
+        writeln!(writer, "interstitial,{},{},{},{},{},{},{},{}", site_columns(&inter.site), inter.atom_index, atom_id,
+                 inter.element, inter.position.0, inter.position.1, inter.position.2, dumbbell)?;
+    }
+    Ok(())
+}
+
+fn site_columns(site: &Site) -> String {
+    format!("{},{},{},{},{},{},{}", site.index, site.coord.0, site.coord.1, site.coord.2,
+            site.position.0, site.position.1, site.position.2)
+}
+
+fn lattice_name(lattice: LatticeType) -> &'static str {
+    match lattice {
+        LatticeType::Bcc => "bcc",
+        LatticeType::Fcc => "fcc",
+    }
+}
+
+fn json_histogram<K: Display>(histogram: &BTreeMap<K, usize>) -> String {
+    json::object(histogram.iter().map(|(k, v)| (k, v.to_string())))
+}
+
+// write the defect summary in json format, the cluster summary is included if `clusters` is specified.
+pub fn write_json_summary<W: Write>(writer: &mut W, result: &WsResult, clusters: Option<&[Cluster]>,
+                                    box_config: &BoxConfig) -> io::Result<()> {
+    let (box_x, box_y, box_z) = box_config.box_size_;
+    let mut items = vec![
+        ("lattice", json::string(lattice_name(box_config.lattice))),
+        ("lattice_const", box_config.lattice_const.to_string()),
+        ("box_size", format!("[{}, {}, {}]", box_x, box_y, box_z)),
+        ("atoms", result.atoms.to_string()),
+        ("sites", result.sites.to_string()),
+        ("skipped_atoms", result.skipped_atoms.to_string()),
+        ("vacancies", result.vacancies.len().to_string()),
+        ("interstitials", result.interstitials.len().to_string()),
+        ("frenkel_pairs", result.frenkel_pairs().to_string()),
+        ("occupancy", json_histogram(&result.occupancy)),
+        ("dumbbell_orientation", json_histogram(&dumbbell::orientation_histogram(&result.interstitials))),
+    ];
+    if let Some(clusters) = clusters {
+        items.push(("clusters", clusters.len().to_string()));
+        items.push(("cluster_size_distribution", json_histogram(&cluster::size_distribution(clusters))));
+    }
+    writeln!(writer, "{}", json::pretty_object(items, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_result() {
+        let mut occupancy = BTreeMap::new();
+        occupancy.insert(0, 1);
+        occupancy.insert(1, 2);
+        occupancy.insert(2, 1);
+        let result = WsResult {
+            atoms: 4,
+            sites: 4,
+            skipped_atoms: 0,
+            vacancies: vec![Site { index: 1, coord: (1, 0, 0), position: (1.5, 1.5, 1.5) }],
+            interstitials: vec![Interstitial {
+                site: Site { index: 2, coord: (2, 0, 0), position: (3.0, 0.0, 0.0) },
+                atom_index: 3,
+                atom_id: Some(4),
+                element: String::from("Fe"),
+                position: (3.5, 0.5, 0.0),
+                dumbbell: Some(Dumbbell::new((1.0, 1.0, 0.0))),
+            }],
+            occupancy,
+        };
+        let mut out: Vec<u8> = Vec::new();
+        write_csv(&mut out, &result).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.ends_with("vacancy,1,1,0,0,1.5,1.5,1.5,,,,,,,,,,\ninterstitial,2,2,0,0,3,0,0,3,4,Fe,3.5,0.5,0,1,1,0,<110>\n"));
+
+        let box_config = BoxConfig::for_test(LatticeType::Bcc, (1, 1, 2), 3.0);
+        let mut out: Vec<u8> = Vec::new();
+        write_json_summary(&mut out, &result, None, &box_config).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\"frenkel_pairs\": 1,\n"));
+        assert!(out.contains("\"occupancy\": {\"0\": 1, \"1\": 2, \"2\": 1},\n"));
+        assert!(out.contains("\"dumbbell_orientation\": {\"<110>\": 1}\n"));
+    }
+}
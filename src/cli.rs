@@ -14,20 +14,62 @@ pub(crate) struct Cli {
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
 pub enum OutFormat {
     Xyz,
+    Extxyz,
     Text,
     Dump,
     Bin,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum InputFormat {
+    // xyz or extended xyz file
+    Xyz,
+    // LAMMPS dump file
+    Dump,
+    // binary MD file
+    Bin,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
 pub enum FormatStandard {
+    // detect the standard from file content, ranks is read from the header of `next` standard
+    Auto,
     Current,
     Next,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum, Debug)]
+pub enum ErrorMode {
+    // |a-b|
+    Absolute,
+    // |a-b|/max(|a|,|b|)
+    Relative,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
+pub enum Boundary {
+    // periodic boundary
+    P,
+    // free boundary
+    F,
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
 pub enum AnsAlgorithm {
+    // Wigner-Seitz analysis for BCC lattice
     WS,
+    // Wigner-Seitz analysis for FCC lattice
+    FccWS,
+    // structure identification (BCC, FCC, HCP, ICO or other) by adaptive common neighbor analysis
+    Cna,
+    // centrosymmetry parameter of each atom
+    Csp,
 }
 
 #[derive(Subcommand)]
@@ -46,25 +88,62 @@ pub(crate) enum Commands {
         format: OutFormat,
         #[clap(short, long, default_value_t = 6, help = "the float number precision")]
         precision: u32,
-        #[clap(short, long, arg_enum, default_value_t = FormatStandard::Current, value_name = "STANDARD", help = "binary file standard")] // default value can not be required.
+        #[clap(short, long, arg_enum, default_value_t = FormatStandard::Auto, value_name = "STANDARD", help = "binary file standard, detected from file content by default")] // default value can not be required.
         standard: FormatStandard,
-        #[clap(short, long, required = true, value_name = "RANKS", help = "ranks to run the parallel program")]
-        ranks: usize,
+        #[clap(short, long, value_name = "RANKS", help = "ranks to run the parallel program, required by binary files of `current` standard")]
+        ranks: Option<usize>,
+        #[clap(long, help = "identify structure type of atoms by adaptive common neighbor analysis (with free boundaries if `--box` is not specified), and write it as column `structure`")]
+        cna: bool,
+        #[clap(short = 'b', long = "box", multiple_values = true, max_values = 9, min_values = 3, help = "the periodic simulation box written to dump and extxyz files: 3 box lengths, or 9 values of cell vectors a, b and c. \
+        If it is not specified, the box is determined by the bounds of atoms (with free boundaries in extxyz files).")]
+        sim_box: Vec<f64>,
+        #[clap(long = "box-origin", multiple_values = true, max_values = 3, min_values = 3, help = "origin of the simulation box specified by `--box` (default: 0 0 0)")]
+        box_origin: Vec<f64>,
+        #[clap(long, value_name = "COLUMNS", help = "comma separated columns written by `dump` and `text` formats, e.g. `id,type,x,y,z,vx,vy,vz,fx,fy,fz,inter_type` (`element` for element name). \
+        Default: `id,type,x,y,z` for dump format, and all available fields for text format.")]
+        columns: Option<String>,
+        #[clap(long, value_name = "START:END:STRIDE", allow_hyphen_values = true, help = "frames to be converted, `end` is exclusive and negative indexes count from the end, \
+        e.g. `::10` for every 10th frame and `-1` for the last frame (default: all frames)")]
+        frames: Option<String>,
     },
     /// diff files
+    /// exit code: 0 for no difference, 1 for mismatched values, 2 for invalid options, 3 for mismatched atoms or frames number, 4 for I/O error.
     #[clap(arg_required_else_help = true)]
-    #[clap(about = "compare particles in two xyz FILES id by id.")]
+    #[clap(about = "compare particles in two xyz (or binary) FILES id by id, frame by frame.")]
     Diff {
         #[clap(short, long, default_value_t = 1e-4, help = "max error")]
         error: f64,
+        #[clap(long = "pos-error", help = "max error of position (default: value of `--error`)")]
+        pos_error: Option<f64>,
+        #[clap(long = "velo-error", help = "max error of velocity (default: value of `--error`)")]
+        velo_error: Option<f64>,
+        #[clap(long = "force-error", help = "max error of force (default: value of `--error`)")]
+        force_error: Option<f64>,
+        #[clap(short = 'm', long = "error-mode", arg_enum, default_value_t = ErrorMode::Absolute, help = "compare with absolute error |a-b| or relative error |a-b|/max(|a|,|b|)")]
+        error_mode: ErrorMode,
+        #[clap(short = 'B', long, help = "compare two binary MD files, instead of xyz files")]
+        binary: bool,
+        #[clap(long, arg_enum, default_value_t = FormatStandard::Auto, value_name = "STANDARD", help = "binary file standard, used with `--binary`")]
+        standard: FormatStandard,
+        #[clap(short, long, value_name = "RANKS", help = "ranks to run the parallel program, required by binary files of `current` standard")]
+        ranks: Option<usize>,
         #[clap(required = true, help = "first file path for `diff`")]
         file_1: String,
         #[clap(required = true, help = "second file path for `diff`")]
         file_2: String,
-        #[clap(short, long, requires = "box", help = "enable/disable periodic boundary checking while performing `diff`")]
+        #[clap(short, long, help = "enable/disable periodic boundary checking (minimum image convention) while performing `diff`")]
         periodic_checking: bool,
-        #[clap(short = 'b', long = "box", group = "box", required = true, multiple_values = true, max_values = 3, min_values = 3, help = "the simulation box length, used for periodic boundary checking.")]
+        #[clap(short = 'b', long = "box", multiple_values = true, max_values = 9, min_values = 3, help = "the simulation box used for periodic boundary checking: 3 box lengths, or 9 values of cell vectors a, b and c. \
+        If it is not specified, the box is read from the `Lattice` header of extxyz file.")]
         sim_box: Vec<f64>,
+        #[clap(long = "box-origin", multiple_values = true, max_values = 3, min_values = 3, help = "origin of the simulation box specified by `--box` (default: 0 0 0)")]
+        box_origin: Vec<f64>,
+        #[clap(short, long = "stop-at-first", help = "stop comparing at the first diverging frame")]
+        stop_at_first: bool,
+        #[clap(long, arg_enum, value_name = "FORMAT", help = "write a machine-readable report of all mismatches")]
+        report: Option<ReportFormat>,
+        #[clap(long = "report-output", value_name = "FILE", requires = "report", help = "file path of the report (default: stdout)")]
+        report_output: Option<String>,
     },
     /// defect analysis
     #[clap(arg_required_else_help = true)]
@@ -82,8 +161,118 @@ pub(crate) enum Commands {
         box_start: Vec<f64>,
         #[clap(short, long, multiple_values = true, max_values = 3, min_values = 3, help = "simulation box size. Use auto detection if not specified.")]
         box_size: Vec<u64>,
-        #[clap(short, long, arg_enum, default_value_t = AnsAlgorithm::WS, help = "algorithm performing defect analysis (ws).")]
+        #[clap(short = 'c', long = "lattice-const", help = "lattice constant (default: 2.85532 for BCC lattice and 3.615 for FCC lattice)")]
+        lattice_const: Option<f64>,
+        #[clap(long = "auto-lattice-const", conflicts_with = "lattice-const", help = "estimate lattice constant from the `Lattice=` box of extxyz files, otherwise from the atoms number and positions")]
+        auto_lattice_const: bool,
+        #[clap(long, arg_enum, multiple_values = true, max_values = 3, min_values = 3, help = "boundary of x, y and z dimension: `p` for periodic and `f` for free (default: p p p)")]
+        boundary: Vec<Boundary>,
+        #[clap(long = "cluster", value_name = "SHELL", possible_values = ["1", "2", "3"], help = "cluster defects connected by 1NN, 2NN or 3NN lattices")]
+        cluster: Option<usize>,
+        #[clap(short = 'T', long = "time-series", conflicts_with = "input-from-minio", help = "analyse all frames in each input file, and write defects of each frame as a time series table")]
+        time_series: bool,
+        #[clap(short = 'B', long, help = "input files are binary MD files, instead of xyz files")]
+        binary: bool,
+        #[clap(long, arg_enum, default_value_t = FormatStandard::Auto, value_name = "STANDARD", help = "binary file standard, used with `--binary`")]
+        standard: FormatStandard,
+        #[clap(short, long, value_name = "RANKS", help = "ranks to run the parallel program, required by binary files of `current` standard")]
+        ranks: Option<usize>,
+        #[clap(short, long, arg_enum, default_value_t = AnsAlgorithm::WS, help = "algorithm performing defect analysis (ws for BCC lattice, fcc-ws for FCC lattice), structure identification (cna) or centrosymmetry parameter (csp).")]
         algorithm: AnsAlgorithm,
+        #[clap(long = "csp-neighbors", value_name = "N", default_value_t = 8, help = "number of nearest neighbors for centrosymmetry parameter (8 for BCC, 12 for FCC)")]
+        csp_neighbors: usize,
+    },
+    /// radial distribution function
+    #[clap(arg_required_else_help = true)]
+    #[clap(about = "compute radial distribution function g(r) (total and partial of each element pair), averaged over all frames.")]
+    Rdf {
+        #[clap(short, long, multiple_values = true, required = true, parse(from_os_str), help = "Sets the filename of input files")]
+        input: Vec<path::PathBuf>,
+        #[clap(short, long, default_value_t = String::from("rdf.txt"), help = "Sets the filename of output file")]
+        output: String,
+        #[clap(short, long, arg_enum, default_value_t = InputFormat::Xyz, value_name = "FORMAT", help = "input format")]
+        format: InputFormat,
+        #[clap(long, arg_enum, default_value_t = FormatStandard::Auto, value_name = "STANDARD", help = "binary file standard, used with `--format bin`")]
+        standard: FormatStandard,
+        #[clap(short, long, value_name = "RANKS", help = "ranks to run the parallel program, required by binary files of `current` standard")]
+        ranks: Option<usize>,
+        #[clap(short, long, default_value_t = 6.0, help = "cutoff distance of g(r)")]
+        cutoff: f64,
+        #[clap(short = 'n', long, default_value_t = 200, help = "number of bins in [0, cutoff)")]
+        bins: usize,
+        #[clap(short = 'b', long = "box", multiple_values = true, max_values = 9, min_values = 3, help = "the periodic simulation box: 3 box lengths, or 9 values of cell vectors a, b and c. \
+        If it is not specified, the box is read from extxyz or dump files, otherwise a box with free boundaries covering all atoms is used.")]
+        sim_box: Vec<f64>,
+        #[clap(long = "box-origin", multiple_values = true, max_values = 3, min_values = 3, help = "origin of the simulation box specified by `--box` (default: 0 0 0)")]
+        box_origin: Vec<f64>,
+        #[clap(short, long, help = "show verbose log")]
+        verbose: bool,
+    },
+    /// mean squared displacement
+    #[clap(arg_required_else_help = true)]
+    #[clap(about = "compute mean squared displacement of atoms (tracked by id) over all frames, and the diffusion coefficient.")]
+    Msd {
+        #[clap(short, long, required = true, parse(from_os_str), help = "Sets the filename of input file")]
+        input: path::PathBuf,
+        #[clap(short, long, default_value_t = String::from("msd.txt"), help = "Sets the filename of output file")]
+        output: String,
+        #[clap(short, long, arg_enum, default_value_t = InputFormat::Bin, value_name = "FORMAT", help = "input format")]
+        format: InputFormat,
+        #[clap(long, arg_enum, default_value_t = FormatStandard::Auto, value_name = "STANDARD", help = "binary file standard, used with `--format bin`")]
+        standard: FormatStandard,
+        #[clap(short, long, value_name = "RANKS", help = "ranks to run the parallel program, required by binary files of `current` standard")]
+        ranks: Option<usize>,
+        #[clap(long, default_value_t = 1.0, help = "time of a step (or a frame if steps are not saved), used if the time is not saved in frame header")]
+        dt: f64,
+        #[clap(short = 'b', long = "box", multiple_values = true, max_values = 9, min_values = 3, help = "the periodic simulation box for unwrapping atoms: 3 box lengths, or 9 values of cell vectors a, b and c. \
+        If it is not specified, the box is read from extxyz or dump files.")]
+        sim_box: Vec<f64>,
+        #[clap(long = "box-origin", multiple_values = true, max_values = 3, min_values = 3, help = "origin of the simulation box specified by `--box` (default: 0 0 0)")]
+        box_origin: Vec<f64>,
+        #[clap(short, long, help = "show verbose log")]
+        verbose: bool,
+    },
+    /// per-atom displacement
+    #[clap(arg_required_else_help = true)]
+    #[clap(about = "compute displacement of atoms (matched by id) from a reference frame, and write it to a dump file.")]
+    Displacement {
+        #[clap(short, long, required = true, parse(from_os_str), help = "Sets the filename of input file")]
+        input: path::PathBuf,
+        #[clap(short, long, default_value_t = String::from("displacement.dump"), help = "Sets the filename of output dump file")]
+        output: String,
+        #[clap(short, long, arg_enum, default_value_t = InputFormat::Bin, value_name = "FORMAT", help = "input format")]
+        format: InputFormat,
+        #[clap(long, arg_enum, default_value_t = FormatStandard::Auto, value_name = "STANDARD", help = "binary file standard, used with `--format bin`")]
+        standard: FormatStandard,
+        #[clap(short, long, value_name = "RANKS", help = "ranks to run the parallel program, required by binary files of `current` standard")]
+        ranks: Option<usize>,
+        #[clap(long = "reference", value_name = "FRAME", default_value_t = 0, help = "index of the reference frame, displacements of atoms in the following frames are computed")]
+        reference: usize,
+        #[clap(short, long, default_value_t = 1.0, help = "atoms displaced beyond the threshold are flagged in column `displaced`")]
+        threshold: f64,
+        #[clap(short = 'b', long = "box", multiple_values = true, max_values = 9, min_values = 3, help = "the periodic simulation box for minimum image convention: 3 box lengths, or 9 values of cell vectors a, b and c. \
+        If it is not specified, the box is read from extxyz or dump files.")]
+        sim_box: Vec<f64>,
+        #[clap(long = "box-origin", multiple_values = true, max_values = 3, min_values = 3, help = "origin of the simulation box specified by `--box` (default: 0 0 0)")]
+        box_origin: Vec<f64>,
+        #[clap(short, long, default_value_t = 6, help = "the float number precision")]
+        precision: u32,
+    },
+    /// metadata of binary files
+    /// exit code: 0 for consistent file, 1 for unreadable file, 2 if inconsistencies are found (listed as warnings).
+    #[clap(arg_required_else_help = true)]
+    #[clap(about = "show metadata (header, frames and atoms of each rank) of a binary MD file, without converting it.")]
+    Info {
+        #[clap(short, long, required = true, parse(from_os_str), help = "Sets the filename of input file")]
+        input: path::PathBuf,
+        #[clap(short, long, arg_enum, default_value_t = FormatStandard::Auto, value_name = "STANDARD", help = "binary file standard")]
+        standard: FormatStandard,
+        #[clap(short, long, value_name = "RANKS", help = "ranks to run the parallel program, required by binary files of `current` standard")]
+        ranks: Option<usize>,
+        #[clap(long, help = "write metadata in json format")]
+        json: bool,
+        #[clap(short, long, value_name = "FILE", help = "file path of the metadata (default: stdout)")]
+        output: Option<String>,
     },
     // #[clap(external_subcommand)]
     // External(Vec<OsString>),
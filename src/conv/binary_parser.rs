@@ -1,8 +1,10 @@
 extern crate libc;
 
 use std::{fmt, error};
-use crate::conv::{binary_parser_v1, binary_parser_v2};
-use crate::conv::binary_types::{BinaryParser};
+use crate::conv::{binary_parser_v1, binary_parser_v2, file_info};
+use crate::conv::binary_types::{BinaryParser, TypeAtom};
+use crate::conv::frame_range::FrameRange;
+use crate::conv::v2_atom_types::{FrameMetaData, TypeDumpMask};
 use crate::conv::writers::out_writer::WriteProgress;
 use crate::cli;
 
@@ -28,36 +30,68 @@ impl error::Error for ParseError {
     }
 }
 
-//on_read: fn (atom: OneAtomType) -> u32
-// select parser for different version of binary format
-pub fn parse_wrapper(bin_standard: cli::FormatStandard, filename: &str, output: &str, ranks: u32, writer: impl WriteProgress)
-                     -> std::result::Result<i32, ParseError> {
-    if bin_standard == cli::FormatStandard::Current {
-        let bin_parser = match binary_parser_v1::make_parser(filename, ranks) {
-            Ok(p) => p,
-            Err(e) => return Err(e)
-        };
-        return parse(output, bin_parser, writer);
-    }
-
-    if bin_standard == cli::FormatStandard::Next {
-        let bin_parser_v2 = match binary_parser_v2::make_parser(filename) {
-            Ok(p) => p,
-            Err(e) => return Err(e)
-        };
-        return parse(output, bin_parser_v2, writer);
+// create parser for different version of binary format
+fn make_parser(bin_standard: cli::FormatStandard, filename: &str, ranks: u32)
+               -> std::result::Result<Box<dyn BinaryParser>, ParseError> {
+    match bin_standard {
+        cli::FormatStandard::Auto => {
+            match file_info::detect_standard(filename, ranks) {
+                Ok(standard) => make_parser(standard, filename, ranks),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    Err(ParseError)
+                }
+            }
+        }
+        cli::FormatStandard::Current => {
+            let bin_parser = binary_parser_v1::make_parser(filename, ranks)?;
+            Ok(Box::new(bin_parser))
+        }
+        cli::FormatStandard::Next => {
+            let bin_parser_v2 = binary_parser_v2::make_parser(filename)?;
+            Ok(Box::new(bin_parser_v2))
+        }
     }
+}
 
-    return Ok(1);
+//on_read: fn (atom: OneAtomType) -> u32
+// select parser for different version of binary format
+// only frames selected by `frame_range` are passed to the writer.
+pub fn parse_wrapper(bin_standard: cli::FormatStandard, filename: &str, output: &str, ranks: u32, writer: impl WriteProgress,
+                     frame_range: FrameRange) -> std::result::Result<i32, ParseError> {
+    let bin_parser = make_parser(bin_standard, filename, ranks)?;
+    parse(output, bin_parser, writer, frame_range)
 }
 
-fn parse(output: &str, mut parser: impl BinaryParser, mut writer: impl WriteProgress)
+fn parse(output: &str, mut parser: Box<dyn BinaryParser>, mut writer: impl WriteProgress, frame_range: FrameRange)
          -> std::result::Result<i32, ParseError> {
     let frames = parser.global_header();
-    writer.on_start(output);
-    for frame in 0..frames {
+    let selected = frame_range.frames(frames);
+    if selected.is_empty() {
+        eprintln!("no frames are selected in the file with {} frame(s).", frames);
+        return Err(ParseError);
+    }
+    if let Err(e) = writer.check_mask(parser.dump_mask()) {
+        eprintln!("{}", e);
+        return Err(ParseError);
+    }
+    writer.on_start(output, parser.dump_mask());
+    // index of the next frame of parser
+    let mut next_frame = 0;
+    for frame in selected {
+        while next_frame < frame {
+            if !parser.skip_frame() {
+                eprintln!("failed to skip frame {}, the file may be truncated.", next_frame);
+                return Err(ParseError);
+            }
+            next_frame += 1;
+        }
         parser.move_to_next_frame();
+        next_frame += 1;
         writer.before_frame(frame, output);
+        if let Some(header) = parser.frame_header(frame) {
+            writer.on_frame_header(&header);
+        }
         while parser.next() {
             let atom = parser.decode();
             if atom.tp != -1 { // invalid atom
@@ -72,3 +106,64 @@ fn parse(output: &str, mut parser: impl BinaryParser, mut writer: impl WriteProg
 
     return Ok(1);
 }
+
+/**
+ * FrameReader reads binary file frame by frame (instead of calling a writer),
+ * and all atoms of a frame are collected into memory.
+ * It is useful for analysing or comparing atoms in binary files directly.
+ */
+pub struct FrameReader {
+    parser: Box<dyn BinaryParser>,
+    frames: u32,
+    next_frame: u32,
+}
+
+pub fn make_frame_reader(bin_standard: cli::FormatStandard, filename: &str, ranks: u32)
+                         -> std::result::Result<FrameReader, ParseError> {
+    let parser = make_parser(bin_standard, filename, ranks)?;
+    let frames = parser.global_header();
+    Ok(FrameReader { parser, frames, next_frame: 0 })
+}
+
+impl FrameReader {
+    // number of frames in the file, read from file header.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    // fields saved for each atom, see `v2_atom_types::MASK_*`
+    pub fn dump_mask(&self) -> TypeDumpMask {
+        self.parser.dump_mask()
+    }
+
+    // header (step and time) of the frame (start from 0), `None` if the file format has no frame header.
+    pub fn frame_header(&mut self, frame: u32) -> Option<FrameMetaData> {
+        self.parser.frame_header(frame)
+    }
+}
+
+impl Iterator for FrameReader {
+    type Item = Vec<TypeAtom>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_frame >= self.frames {
+            return None;
+        }
+        self.parser.move_to_next_frame();
+        let mut atoms = Vec::new();
+        while self.parser.next() {
+            let atom = self.parser.decode();
+            if atom.tp != -1 { // invalid atom
+                atoms.push(atom);
+            }
+        }
+        self.next_frame += 1;
+        Some(atoms)
+    }
+}
+
+impl Drop for FrameReader {
+    fn drop(&mut self) {
+        self.parser.close();
+    }
+}
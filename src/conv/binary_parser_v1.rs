@@ -1,6 +1,6 @@
 use std::ffi::CString;
 
-use crate::conv::{binary_types, lib_conv_capi};
+use crate::conv::{binary_types, lib_conv_capi, v2_atom_types};
 use crate::conv::binary_parser::ParseError;
 
 pub struct BinaryParserV1 {
@@ -43,6 +43,11 @@ impl binary_types::BinaryParser for BinaryParserV1 {
         return 1;
     }
 
+    fn dump_mask(&self) -> v2_atom_types::TypeDumpMask {
+        // position and velocity are always saved in the old format.
+        v2_atom_types::MASK_POSITION | v2_atom_types::MASK_VELOCITY
+    }
+
     fn next(&mut self) -> bool {
         let ok = unsafe {
             lib_conv_capi::read_next_atom(self.c_parser, &mut self.atom as *mut lib_conv_capi::type_c_atom)
@@ -66,7 +71,10 @@ impl binary_types::BinaryParser for BinaryParserV1 {
         return false;
     }
 
-    fn frame_header(&self) {}
+    // frame header is not saved in v1 format.
+    fn frame_header(&mut self, _frame: u32) -> Option<v2_atom_types::FrameMetaData> {
+        None
+    }
 
     fn close(&self) {
         unsafe {
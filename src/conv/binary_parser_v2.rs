@@ -25,7 +25,10 @@ pub struct BinaryParserV2 {
 // initialize parser
 pub fn make_parser(filename: &str)
                    -> std::result::Result<BinaryParserV2, ParseError> {
-    let mut input_file = File::open(filename).unwrap();
+    let mut input_file = match File::open(filename) {
+        Ok(f) => f,
+        Err(_e) => return Err(ParseError),
+    };
 
     let global_header_size = std::mem::size_of::<v2_atom_types::GlobalMetaData>();
     let mut buffer = [0; std::mem::size_of::<v2_atom_types::GlobalMetaData>()];
@@ -38,7 +41,7 @@ pub fn make_parser(filename: &str)
 
     // parse global header
     if n != global_header_size {
-        println!("bad global header size. file format version may be not incompatible");
+        eprintln!("bad global header size. file format version may be not incompatible");
         return Err(ParseError);
     } else {
         let global_header: v2_atom_types::GlobalMetaData = v2_atom_types::GlobalMetaData::read_bytes(&buffer[..]);
@@ -47,8 +50,7 @@ pub fn make_parser(filename: &str)
         let mut rank_offset_vec = Vec::with_capacity(global_header.mpi_ranks as usize);
         rank_offset_vec.resize(global_header.mpi_ranks as usize, (0, 0_u32));
         for i in 0..rank_offset_vec.len() {
-            let base_cursor: u64 = global_header.self_size + (global_header.frames as u64) * global_header.frame_meta_size
-                + global_header.mpi_ranks * global_header.local_size;
+            let base_cursor: u64 = global_header.data_offset();
             rank_offset_vec[i] = (base_cursor + (i as u64) * global_header.block_atoms * global_header.atom_item_bytes, 0_u32);
         };
 
@@ -80,19 +82,19 @@ impl BinaryParserV2 {
 
         let mut cursor = v2_atom_types::AtomInfoDump::size_in_file();
 
-        if self.global_header.mask & (1 << 0) != 0 {
+        if self.global_header.mask & v2_atom_types::MASK_POSITION != 0 {
             let left: &[u8] = &buffer[cursor..];
             let atom_pos = v2_atom_types::AtomDumpData3D::read_bytes(&left[..]);
             self.atom.atom_location = atom_pos.atom_props;
             cursor += std::mem::size_of::<v2_atom_types::AtomDumpData3D>();
         }
-        if self.global_header.mask & (1 << 1) != 0 {
+        if self.global_header.mask & v2_atom_types::MASK_VELOCITY != 0 {
             let left: &[u8] = &buffer[cursor..];
             let atom_v = v2_atom_types::AtomDumpData3D::read_bytes(&left[..]);
             self.atom.atom_velocity = atom_v.atom_props;
             cursor += std::mem::size_of::<v2_atom_types::AtomDumpData3D>();
         }
-        if self.global_header.mask & (1 << 2) != 0 {
+        if self.global_header.mask & v2_atom_types::MASK_FORCE != 0 {
             let left: &[u8] = &buffer[cursor..];
             let atom_f = v2_atom_types::AtomDumpData3D::read_bytes(&left[..]);
             self.atom.atom_force = atom_f.atom_props;
@@ -119,6 +121,39 @@ impl BinaryParserV2 {
         self.cur_index_in_block = offset.1;
     }
 
+    // skip atoms of `rank` in current frame, by finding the terminating item (type -1) in blocks of the rank,
+    // and seeking over blocks of other ranks.
+    // The offset and index in block of the rank in next frame is returned, or `None` if the end of file is reached.
+    fn skip_rank_frame(&mut self, rank: usize) -> Option<(u64, u32)> {
+        let item_bytes = self.global_header.atom_item_bytes;
+        let block_atoms = self.global_header.block_atoms;
+        let (mut offset, mut index) = self.rank_start_offset[rank];
+        let mut buffer = Vec::new();
+        loop {
+            if (index as u64) >= block_atoms {
+                offset += block_atoms * (self.global_header.mpi_ranks - 1) * item_bytes;
+                index = 0;
+            }
+            // read the left items in current block
+            let left = block_atoms - index as u64;
+            buffer.clear();
+            self.file.seek(std::io::SeekFrom::Start(offset)).ok()?;
+            (&mut self.file).take(left * item_bytes).read_to_end(&mut buffer).ok()?;
+            let items = buffer.len() / item_bytes as usize;
+            for i in 0..items {
+                let atom_data = v2_atom_types::AtomInfoDump::read_bytes(&buffer[i * item_bytes as usize..]);
+                if atom_data.type_ == -1 {
+                    return Some((offset + (i as u64 + 1) * item_bytes, index + i as u32 + 1));
+                }
+            }
+            if (items as u64) < left {
+                return None;
+            }
+            offset += left * item_bytes;
+            index += left as u32;
+        }
+    }
+
     fn file_tell(&mut self) -> u64 {
         return self.file.seek(std::io::SeekFrom::Current(0)).unwrap();
     }
@@ -126,11 +161,13 @@ impl BinaryParserV2 {
 
 impl binary_types::BinaryParser for BinaryParserV2 {
     fn global_header(&self) -> u32 {
-        println!("file metadata:");
-        println!("{:#?}", self.global_header);
         return self.global_header.frames;
     }
 
+    fn dump_mask(&self) -> v2_atom_types::TypeDumpMask {
+        self.global_header.mask
+    }
+
     // read next atom in one frame
     fn next(&mut self) -> bool {
         self.try_switch_to_next_block();
@@ -154,7 +191,6 @@ impl binary_types::BinaryParser for BinaryParserV2 {
             self.switch_to_next_rank();
             if ((rank_before_switch + 1) as u64) >= self.global_header.mpi_ranks {
                 // current frame end
-                println!("current frame end");
                 return false;
             }
         }
@@ -178,7 +214,41 @@ impl binary_types::BinaryParser for BinaryParserV2 {
         return true;
     }
 
-    fn frame_header(&self) {}
+    // skip the frame by seeking with the block layout, only the atom types are read to find the end of each rank.
+    fn skip_frame(&mut self) -> bool {
+        if self.next_frame >= self.global_header.frames {
+            return false;
+        }
+        for rank in 0..self.rank_start_offset.len() {
+            match self.skip_rank_frame(rank) {
+                Some(start) => self.rank_start_offset[rank] = start,
+                None => return false,
+            }
+        }
+        self.next_frame += 1;
+        return true;
+    }
+
+    // read the frame header, the file cursor is restored after reading.
+    fn frame_header(&mut self, frame: u32) -> Option<v2_atom_types::FrameMetaData> {
+        if frame >= self.global_header.frames {
+            return None;
+        }
+        let meta_size = std::mem::size_of::<v2_atom_types::FrameMetaData>();
+        if (self.global_header.frame_meta_size as usize) < meta_size {
+            return None;
+        }
+        let cursor = self.file_tell();
+        let offset = self.global_header.self_size + (frame as u64) * self.global_header.frame_meta_size;
+        let mut buffer = vec![0; meta_size];
+        let read = self.file.seek(std::io::SeekFrom::Start(offset))
+            .and_then(|_| self.file.read_exact(&mut buffer[..]));
+        self.file.seek(std::io::SeekFrom::Start(cursor)).unwrap();
+        match read {
+            Ok(_) => Some(v2_atom_types::FrameMetaData::read_bytes(&buffer[..])),
+            Err(_) => None,
+        }
+    }
 
     fn close(&self) {}
 }
@@ -1,3 +1,5 @@
+use crate::conv::v2_atom_types::{FrameMetaData, TypeDumpMask};
+
 /**
  * basic atom type saved in binary atom file.
  * each atom in binary atom file will include those information below.
@@ -28,19 +30,39 @@ impl TypeAtom {
             _ => "Unknown",
         }
     }
+
+    // the inverse of `get_name_by_ele_name`, `None` for unknown element names.
+    pub fn get_type_by_ele_name(name: &str) -> Option<i32> {
+        match name {
+            "V" => Some(-1),
+            "Fe" => Some(0),
+            "Cu" => Some(1),
+            "Ni" => Some(2),
+            _ => None,
+        }
+    }
 }
 
 pub trait BinaryParser {
     // return get total frames in global header
     fn global_header(&self) -> u32;
+    // fields (position, velocity, force) saved for each atom, see `v2_atom_types::MASK_*`
+    fn dump_mask(&self) -> TypeDumpMask;
     // move next atom
     fn next(&mut self) -> bool;
     // decode atom struct in current position
     fn decode(&mut self) -> TypeAtom;
     // move to next frame
     fn move_to_next_frame(&mut self) -> bool;
-    // get frame header
-    fn frame_header(&self);
+    // skip the next frame without decoding its atoms, `false` is returned if the frame can not be skipped.
+    // By default, atoms of the frame are read and dropped.
+    fn skip_frame(&mut self) -> bool {
+        self.move_to_next_frame();
+        while self.next() {}
+        true
+    }
+    // get header of the frame (start from 0), `None` is returned if there is no frame header in file.
+    fn frame_header(&mut self, frame: u32) -> Option<FrameMetaData>;
     // close parser
     fn close(&self);
 }
@@ -0,0 +1,438 @@
+// Metadata of binary MD files (`info` sub-command): the global header, frame headers and atoms saved by each rank.
+// Atoms are only counted, not converted.
+// The standard of binary files can also be detected from the metadata.
+
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use byte_struct::ByteStruct;
+
+use crate::cli;
+use crate::conv::{binary_parser_v2, lib_conv_capi};
+use crate::conv::binary_types::BinaryParser;
+use crate::conv::v2_atom_types::{self, GlobalMetaData, TypeDumpMask, MASK_FORCE, MASK_POSITION, MASK_VELOCITY};
+use crate::json;
+
+// layout of files in `current` standard, see `src/convert/converter.c`.
+const V1_HEADER_SIZE: u64 = 128;
+const V1_LOCAL_HEADER_SIZE: u64 = 128;
+const V1_BLOCK_ATOMS: u64 = 1024;
+
+pub struct FrameInfo {
+    // step and time in frame header, `None` if there is no frame header in file.
+    pub step: Option<u64>,
+    pub time: Option<f64>,
+    // atoms number in frame header
+    pub header_atoms: Option<u64>,
+    // atoms saved by each rank in this frame
+    pub rank_atoms: Vec<u64>,
+}
+
+impl FrameInfo {
+    pub fn atoms(&self) -> u64 {
+        self.rank_atoms.iter().sum()
+    }
+}
+
+pub struct FileInfo {
+    pub format_version: u32,
+    pub mask: TypeDumpMask,
+    pub ranks: u64,
+    pub block_atoms: u64,
+    pub atom_item_bytes: u64,
+    pub atoms_num: u64,
+    pub frames: Vec<FrameInfo>,
+    // inconsistencies found in file, e.g. atoms number in frame header differs from the atoms saved by ranks.
+    pub warnings: Vec<String>,
+}
+
+// names of fields saved for each atom.
+pub fn mask_fields(mask: TypeDumpMask) -> Vec<&'static str> {
+    [(MASK_POSITION, "position"), (MASK_VELOCITY, "velocity"), (MASK_FORCE, "force")].iter()
+        .filter(|(bit, _)| mask & bit != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+// read metadata of a binary file, `ranks` is only used by files of `current` standard.
+pub fn read_info(bin_standard: cli::FormatStandard, filename: &str, ranks: u32) -> Result<FileInfo, String> {
+    match bin_standard {
+        cli::FormatStandard::Auto => read_info(detect_standard(filename, ranks)?, filename, ranks),
+        cli::FormatStandard::Current => read_v1_info(filename, ranks),
+        cli::FormatStandard::Next => read_v2_info(filename),
+    }
+}
+
+/**
+ * detect the standard of a binary file from its content.
+ * Files of `next` standard are recognized by the global header (format version, mask, atom item size)
+ * and the file size required by the header.
+ * Otherwise, the file is checked against the layout of `current` standard, whose ranks are not saved in file,
+ * thus `ranks` must be given (0 for unknown ranks).
+ */
+pub fn detect_standard(filename: &str, ranks: u32) -> Result<cli::FormatStandard, String> {
+    let mut file = File::open(filename).map_err(|e| format!("failed to open file {}: {}", filename, e))?;
+    let file_size = file.metadata().map_err(|e| format!("failed to read file {}: {}", filename, e))?.len();
+    let v2_error = match check_v2_header(&mut file, file_size) {
+        Ok(_) => return Ok(cli::FormatStandard::Next),
+        Err(e) => e,
+    };
+    if ranks == 0 {
+        return Err(format!("file {} is not in `next` standard ({}), ranks must be specified to read it as `current` standard.",
+                           filename, v2_error));
+    }
+    let v1_error = match read_v1_rank_atoms(&mut file, ranks) {
+        Ok(rank_atoms) => {
+            let expected_size = v1_file_size(&rank_atoms, v1_atom_item_bytes());
+            if file_size >= expected_size {
+                return Ok(cli::FormatStandard::Current);
+            }
+            format!("{} bytes are required by atoms of {} ranks, but the file size is {} bytes", expected_size, ranks, file_size)
+        }
+        Err(e) => e,
+    };
+    Err(format!("file {} matches neither binary format: not in `next` standard ({}), and not in `current` standard ({}).",
+                filename, v2_error, v1_error))
+}
+
+// read and check the global header of `next` standard, including the file size required by the header.
+fn check_v2_header<R: Read>(reader: &mut R, file_size: u64) -> Result<GlobalMetaData, String> {
+    let mut buffer = [0_u8; std::mem::size_of::<GlobalMetaData>()];
+    if reader.read_exact(&mut buffer).is_err() {
+        return Err(String::from("file is smaller than the global header"));
+    }
+    let header = GlobalMetaData::read_bytes(&buffer[..]);
+    if header.format_version < 2 {
+        return Err(format!("unsupported format version {}", header.format_version));
+    }
+    if header.mask == 0 || header.mask & !(MASK_POSITION | MASK_VELOCITY | MASK_FORCE) != 0 {
+        return Err(format!("invalid mask {}", header.mask));
+    }
+    if header.mpi_ranks == 0 || header.block_atoms == 0 {
+        return Err(format!("invalid ranks {} or block atoms {}", header.mpi_ranks, header.block_atoms));
+    }
+    let item_bytes = v2_atom_types::AtomInfoDump::size_in_file() as u64
+        + mask_fields(header.mask).len() as u64 * std::mem::size_of::<v2_atom_types::AtomDumpData3D>() as u64;
+    if header.atom_item_bytes < item_bytes {
+        return Err(format!("atom item size {} is smaller than {} bytes required by the mask", header.atom_item_bytes, item_bytes));
+    }
+    // each rank saves at least one block (for the terminating item of each frame).
+    let required_size = (header.frames as u64).checked_mul(header.frame_meta_size)
+        .and_then(|size| size.checked_add(header.self_size))
+        .and_then(|size| header.mpi_ranks.checked_mul(header.local_size)?.checked_add(size))
+        .and_then(|size| {
+            if header.frames == 0 {
+                return Some(size);
+            }
+            let block_bytes = header.block_atoms.checked_mul(header.atom_item_bytes)?;
+            (header.mpi_ranks - 1).checked_mul(block_bytes)?.checked_add(header.atom_item_bytes)?.checked_add(size)
+        });
+    match required_size {
+        Some(size) if size <= file_size => Ok(header),
+        Some(size) => Err(format!("{} bytes are required by the header, but the file size is {} bytes", size, file_size)),
+        None => Err(String::from("invalid sizes in the global header")),
+    }
+}
+
+fn v1_atom_item_bytes() -> u64 {
+    std::mem::size_of::<lib_conv_capi::type_c_atom>() as u64
+}
+
+// atoms number of each rank, saved in local headers of `current` standard.
+fn read_v1_rank_atoms<R: Read + Seek>(reader: &mut R, ranks: u32) -> Result<Vec<u64>, String> {
+    let mut rank_atoms = Vec::with_capacity(ranks as usize);
+    for rank in 0..ranks as u64 {
+        let mut buffer = [0_u8; 8];
+        reader.seek(SeekFrom::Start(V1_HEADER_SIZE + rank * V1_LOCAL_HEADER_SIZE))
+            .and_then(|_| reader.read_exact(&mut buffer))
+            .map_err(|e| format!("failed to read local header of rank {}: {}", rank, e))?;
+        rank_atoms.push(u64::from_le_bytes(buffer));
+    }
+    Ok(rank_atoms)
+}
+
+// the `current` standard only has atoms number of each rank in local headers, and there is only one frame.
+fn read_v1_info(filename: &str, ranks: u32) -> Result<FileInfo, String> {
+    let mut file = File::open(filename).map_err(|e| format!("failed to open file {}: {}", filename, e))?;
+    let rank_atoms = read_v1_rank_atoms(&mut file, ranks)?;
+
+    let atom_item_bytes = v1_atom_item_bytes();
+    let mut warnings = Vec::new();
+    let expected_size = v1_file_size(&rank_atoms, atom_item_bytes);
+    let file_size = file.metadata().map_err(|e| format!("failed to read file {}: {}", filename, e))?.len();
+    if file_size < expected_size {
+        warnings.push(format!("file is truncated: {} bytes are required by the atoms of ranks, but the file size is {} bytes",
+                              expected_size, file_size));
+    }
+    Ok(FileInfo {
+        format_version: 1,
+        mask: MASK_POSITION | MASK_VELOCITY,
+        ranks: ranks as u64,
+        block_atoms: V1_BLOCK_ATOMS,
+        atom_item_bytes,
+        atoms_num: rank_atoms.iter().sum(),
+        frames: vec![FrameInfo { step: None, time: None, header_atoms: None, rank_atoms }],
+        warnings,
+    })
+}
+
+// minimal file size of `current` standard, where the atoms of each rank are saved in blocks interleaved with other ranks.
+fn v1_file_size(rank_atoms: &[u64], atom_item_bytes: u64) -> u64 {
+    let ranks = rank_atoms.len() as u64;
+    let block_bytes = V1_BLOCK_ATOMS * atom_item_bytes;
+    let data_offset = V1_HEADER_SIZE + V1_LOCAL_HEADER_SIZE * ranks;
+    // saturated for atoms number read from a file in other format.
+    rank_atoms.iter().enumerate().filter(|(_, atoms)| **atoms != 0).map(|(rank, atoms)| {
+        let bytes = atoms.saturating_mul(atom_item_bytes);
+        let last_block = (bytes - 1) / block_bytes;
+        (last_block.saturating_mul(ranks).saturating_add(rank as u64)).saturating_mul(block_bytes)
+            .saturating_add(data_offset + (bytes - last_block * block_bytes))
+    }).max().unwrap_or(data_offset)
+}
+
+fn read_v2_info(filename: &str) -> Result<FileInfo, String> {
+    let mut parser = binary_parser_v2::make_parser(filename)
+        .map_err(|e| format!("failed to read global header of file {}: {}", filename, e))?;
+    let header = parser.global_header;
+    let mut file = File::open(filename).map_err(|e| format!("failed to open file {}: {}", filename, e))?;
+    let file_size = file.metadata().map_err(|e| format!("failed to read file {}: {}", filename, e))?.len();
+    check_v2_header(&mut file, file_size).map_err(|e| format!("invalid global header of file {}: {}", filename, e))?;
+    let mut frames: Vec<FrameInfo> = (0..header.frames).map(|frame| {
+        let meta = parser.frame_header(frame);
+        FrameInfo {
+            step: meta.map(|m| m.step),
+            time: meta.map(|m| m.time),
+            header_atoms: meta.map(|m| m.atoms_num),
+            rank_atoms: Vec::with_capacity(header.mpi_ranks as usize),
+        }
+    }).collect();
+    parser.close();
+    count_rank_atoms(&mut file, &header, &mut frames)?;
+
+    let mut warnings = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        match frame.header_atoms {
+            Some(atoms) if atoms != frame.atoms() => {
+                warnings.push(format!("frame {}: {} atoms in frame header, but {} atoms are saved by ranks", index, atoms, frame.atoms()));
+            }
+            None => warnings.push(format!("frame {}: frame header is not found", index)),
+            _ => {}
+        }
+    }
+    Ok(FileInfo {
+        format_version: header.format_version,
+        mask: header.mask,
+        ranks: header.mpi_ranks,
+        block_atoms: header.block_atoms,
+        atom_item_bytes: header.atom_item_bytes,
+        atoms_num: header.atoms_num,
+        frames,
+        warnings,
+    })
+}
+
+// count atoms of each rank in each frame, by walking through atom items of the rank until the terminating item (type -1).
+fn count_rank_atoms<R: Read + Seek>(reader: &mut R, header: &GlobalMetaData, frames: &mut [FrameInfo]) -> Result<(), String> {
+    for rank in 0..header.mpi_ranks {
+        let mut stream = RankStream { reader: &mut *reader, header, rank, next_block: 0, buffer: Vec::new(), items: 0, cursor: 0 };
+        for (index, frame) in frames.iter_mut().enumerate() {
+            let mut atoms = 0;
+            loop {
+                match stream.next_type().map_err(|e| format!("failed to read atoms of rank {}: {}", rank, e))? {
+                    Some(-1) => break,
+                    Some(_) => atoms += 1,
+                    None => return Err(format!("file is truncated: atoms of rank {} in frame {} are not terminated", rank, index)),
+                }
+            }
+            frame.rank_atoms.push(atoms);
+        }
+    }
+    Ok(())
+}
+
+// atom items of one rank, which are saved in blocks interleaved with blocks of other ranks.
+struct RankStream<'a, R: Read + Seek> {
+    reader: &'a mut R,
+    header: &'a GlobalMetaData,
+    rank: u64,
+    // index of next block of this rank
+    next_block: u64,
+    buffer: Vec<u8>,
+    // items number in buffer, and index of next item in buffer
+    items: usize,
+    cursor: usize,
+}
+
+impl<'a, R: Read + Seek> RankStream<'a, R> {
+    // atom type of next item, `None` is returned at the end of file.
+    fn next_type(&mut self) -> io::Result<Option<i32>> {
+        if self.cursor >= self.items && !self.read_block()? {
+            return Ok(None);
+        }
+        let offset = self.cursor * self.header.atom_item_bytes as usize + std::mem::size_of::<v2_atom_types::TypeAtomId>();
+        let tp = i32::from_le_bytes(self.buffer[offset..offset + 4].try_into().unwrap());
+        self.cursor += 1;
+        Ok(Some(tp))
+    }
+
+    fn read_block(&mut self) -> io::Result<bool> {
+        let block_bytes = self.header.block_atoms * self.header.atom_item_bytes;
+        let offset = self.header.data_offset() + (self.next_block * self.header.mpi_ranks + self.rank) * block_bytes;
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.buffer.clear();
+        // the last block may be incomplete if the file is truncated.
+        (&mut *self.reader).take(block_bytes).read_to_end(&mut self.buffer)?;
+        self.items = self.buffer.len() / self.header.atom_item_bytes as usize;
+        self.cursor = 0;
+        self.next_block += 1;
+        Ok(self.items > 0)
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>, none: &str) -> String {
+    value.map_or(String::from(none), |v| v.to_string())
+}
+
+// write metadata as text or json.
+pub fn write_info<W: Write>(writer: &mut W, filename: &str, info: &FileInfo, json: bool) -> io::Result<()> {
+    if json {
+        write_json(writer, filename, info)
+    } else {
+        write_text(writer, filename, info)
+    }
+}
+
+fn write_text<W: Write>(writer: &mut W, filename: &str, info: &FileInfo) -> io::Result<()> {
+    writeln!(writer, "file: {}", filename)?;
+    writeln!(writer, "format version: {}", info.format_version)?;
+    writeln!(writer, "fields: {} (mask {})", mask_fields(info.mask).join(", "), info.mask)?;
+    writeln!(writer, "ranks: {}", info.ranks)?;
+    writeln!(writer, "block atoms: {}", info.block_atoms)?;
+    writeln!(writer, "atom item bytes: {}", info.atom_item_bytes)?;
+    writeln!(writer, "atoms: {}", info.atoms_num)?;
+    writeln!(writer, "frames: {}", info.frames.len())?;
+    for (index, frame) in info.frames.iter().enumerate() {
+        let rank_atoms: Vec<String> = frame.rank_atoms.iter().map(|atoms| atoms.to_string()).collect();
+        writeln!(writer, "frame {}: step {}, time {}, atoms {}, atoms of ranks: {}", index, optional(frame.step, "-"),
+                 optional(frame.time, "-"), frame.atoms(), rank_atoms.join(" "))?;
+    }
+    for warning in &info.warnings {
+        writeln!(writer, "warning: {}", warning)?;
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(writer: &mut W, filename: &str, info: &FileInfo) -> io::Result<()> {
+    let fields: Vec<String> = mask_fields(info.mask).iter().map(|f| json::string(f)).collect();
+    let frames: Vec<String> = info.frames.iter().enumerate().map(|(index, frame)| {
+        let rank_atoms: Vec<String> = frame.rank_atoms.iter().map(|atoms| atoms.to_string()).collect();
+        format!("{{\"frame\": {}, \"step\": {}, \"time\": {}, \"header_atoms\": {}, \"atoms\": {}, \"rank_atoms\": {}}}",
+                index, optional(frame.step, "null"), json::optional(frame.time.map(json::number)),
+                optional(frame.header_atoms, "null"), frame.atoms(), json::array(&rank_atoms))
+    }).collect();
+    let warnings: Vec<String> = info.warnings.iter().map(|w| json::string(w)).collect();
+    let items = vec![
+        ("file", json::string(filename)),
+        ("format_version", info.format_version.to_string()),
+        ("mask", info.mask.to_string()),
+        ("fields", json::array(&fields)),
+        ("ranks", info.ranks.to_string()),
+        ("block_atoms", info.block_atoms.to_string()),
+        ("atom_item_bytes", info.atom_item_bytes.to_string()),
+        ("atoms_num", info.atoms_num.to_string()),
+        ("frames_num", info.frames.len().to_string()),
+        ("frames", json::pretty_array(&frames, 2)),
+        ("warnings", json::pretty_array(&warnings, 2)),
+    ];
+    writeln!(writer, "{}", json::pretty_object(items, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // atom item of v2 format with position only.
+    fn item(id: u64, tp: i32) -> Vec<u8> {
+        let mut bytes = id.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&tp.to_le_bytes());
+        bytes.extend_from_slice(&[0_u8; 24]);
+        bytes
+    }
+
+    #[test]
+    fn test_count_rank_atoms() {
+        let header = GlobalMetaData {
+            self_size: 80,
+            frame_meta_size: 32,
+            block_atoms: 2,
+            atoms_num: 3,
+            atom_item_bytes: 36,
+            mpi_ranks: 2,
+            mask: MASK_POSITION,
+            format_version: 2,
+            global_header_size: 80,
+            local_size: 0,
+            frames: 2,
+        };
+        // rank 0: 2 atoms in frame 0 and 1 atom in frame 1, rank 1: 1 atom in frame 0 and no atoms in frame 1.
+        let rank0 = [item(1, 0), item(2, 0), item(0, -1), item(1, 0), item(0, -1)];
+        let rank1 = [item(3, 1), item(0, -1), item(0, -1)];
+        let mut data = vec![0_u8; header.data_offset() as usize];
+        for block in 0..3 {
+            for rank in [&rank0[..], &rank1[..]].iter() {
+                for i in 0..2 {
+                    data.extend(rank.get(block * 2 + i).cloned().unwrap_or_else(|| vec![0_u8; 36]));
+                }
+            }
+        }
+        let frame = || FrameInfo { step: None, time: None, header_atoms: None, rank_atoms: Vec::new() };
+        let mut frames = vec![frame(), frame()];
+        count_rank_atoms(&mut Cursor::new(&data), &header, &mut frames).unwrap();
+        assert_eq!(frames[0].rank_atoms, vec![2, 1]);
+        assert_eq!(frames[1].rank_atoms, vec![1, 0]);
+
+        // the file is truncated in the last block of rank 0.
+        data.truncate(header.data_offset() as usize + 4 * 2 * 36);
+        let mut frames = vec![frame(), frame()];
+        assert!(count_rank_atoms(&mut Cursor::new(&data), &header, &mut frames).unwrap_err().contains("rank 0 in frame 1"));
+
+        assert_eq!(mask_fields(MASK_POSITION | MASK_FORCE), vec!["position", "force"]);
+    }
+
+    #[test]
+    fn test_check_v2_header() {
+        let mut header = GlobalMetaData {
+            self_size: 80,
+            frame_meta_size: 32,
+            block_atoms: 4,
+            atoms_num: 8,
+            atom_item_bytes: 60,
+            mpi_ranks: 2,
+            mask: MASK_POSITION | MASK_VELOCITY,
+            format_version: 2,
+            global_header_size: 80,
+            local_size: 0,
+            frames: 1,
+        };
+        let bytes = |header: &GlobalMetaData| {
+            let mut bytes = vec![0_u8; std::mem::size_of::<GlobalMetaData>()];
+            header.write_bytes(&mut bytes[..]);
+            bytes
+        };
+        // global header, one frame header, one block of rank 0 and the terminating item of rank 1.
+        let size = 80 + 32 + 4 * 60 + 60;
+        assert!(check_v2_header(&mut &bytes(&header)[..], size).is_ok());
+        assert!(check_v2_header(&mut &bytes(&header)[..], size - 1).unwrap_err().contains("file size"));
+        assert!(check_v2_header(&mut &bytes(&header)[..40], size).is_err());
+
+        header.atom_item_bytes = 36;
+        assert!(check_v2_header(&mut &bytes(&header)[..], size).unwrap_err().contains("required by the mask"));
+        header.mask = 8;
+        assert!(check_v2_header(&mut &bytes(&header)[..], size).unwrap_err().contains("invalid mask"));
+        // header of `current` standard is not recognized.
+        assert!(check_v2_header(&mut &[0_u8; 128][..], size).is_err());
+        assert_eq!(v1_file_size(&[1024, 1, 0], 72), 128 + 128 * 3 + 1024 * 72 + 72);
+    }
+}
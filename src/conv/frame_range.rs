@@ -0,0 +1,85 @@
+// Frames selected by `--frames start:end:stride` option of `conv`, in the same way as slices of python:
+// `end` is exclusive, negative `start` and `end` count from the end, and omitted values select from the first
+// frame or to the last frame. A single index (e.g. `-1` for the last frame) selects only one frame.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FrameRange {
+    start: Option<i64>,
+    end: Option<i64>,
+    stride: u32,
+}
+
+impl FrameRange {
+    // select all frames
+    pub fn all() -> FrameRange {
+        FrameRange { start: None, end: None, stride: 1 }
+    }
+
+    pub fn parse(spec: &str) -> Result<FrameRange, String> {
+        let parts: Vec<&str> = spec.split(':').map(|part| part.trim()).collect();
+        let index = |part: &str| -> Result<Option<i64>, String> {
+            if part.is_empty() {
+                return Ok(None);
+            }
+            part.parse::<i64>().map(Some).map_err(|_| format!("invalid frame index `{}` in frame range `{}`", part, spec))
+        };
+        match parts.len() {
+            1 => {
+                let start = index(parts[0])?
+                    .ok_or_else(|| format!("invalid frame range `{}`, expected `start:end:stride`", spec))?;
+                // the end of the last frame (-1) is the end of file.
+                let end = if start == -1 { None } else { Some(start + 1) };
+                Ok(FrameRange { start: Some(start), end, stride: 1 })
+            }
+            2 | 3 => {
+                let stride = match parts.get(2) {
+                    Some(stride) if !stride.is_empty() => match stride.parse::<u32>() {
+                        Ok(stride) if stride > 0 => stride,
+                        _ => return Err(format!("invalid stride `{}` in frame range `{}`, it must be a positive integer", stride, spec)),
+                    },
+                    _ => 1,
+                };
+                Ok(FrameRange { start: index(parts[0])?, end: index(parts[1])?, stride })
+            }
+            _ => Err(format!("invalid frame range `{}`, expected `start:end:stride`", spec)),
+        }
+    }
+
+    // indexes of selected frames in a file with `frames` frames, in ascending order.
+    pub fn frames(&self, frames: u32) -> Vec<u32> {
+        let resolve = |index: i64| -> u32 {
+            if index < 0 {
+                (frames as i64 + index).max(0) as u32
+            } else {
+                index.min(frames as i64) as u32
+            }
+        };
+        let start = self.start.map_or(0, resolve);
+        let end = self.end.map_or(frames, resolve);
+        (start..end).step_by(self.stride as usize).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_range() {
+        assert_eq!(FrameRange::all().frames(3), vec![0, 1, 2]);
+        assert_eq!(FrameRange::parse("::10").unwrap().frames(25), vec![0, 10, 20]);
+        assert_eq!(FrameRange::parse("1:7:2").unwrap().frames(25), vec![1, 3, 5]);
+        assert_eq!(FrameRange::parse("5:").unwrap().frames(7), vec![5, 6]);
+        assert_eq!(FrameRange::parse("-1").unwrap().frames(7), vec![6]);
+        assert_eq!(FrameRange::parse("-3:-1").unwrap().frames(7), vec![4, 5]);
+        assert_eq!(FrameRange::parse("2").unwrap().frames(7), vec![2]);
+        // out of range
+        assert!(FrameRange::parse("8").unwrap().frames(7).is_empty());
+        assert_eq!(FrameRange::parse("-10:2").unwrap().frames(7), vec![0, 1]);
+
+        assert!(FrameRange::parse("1:2:0").is_err());
+        assert!(FrameRange::parse("a:2").is_err());
+        assert!(FrameRange::parse("1:2:3:4").is_err());
+        assert!(FrameRange::parse("").is_err());
+    }
+}
@@ -2,6 +2,8 @@ pub(crate) mod binary_types;
 pub(crate) mod binary_parser;
 mod binary_parser_v1;
 mod binary_parser_v2;
-mod v2_atom_types;
+pub(crate) mod file_info;
+pub(crate) mod frame_range;
+pub(crate) mod v2_atom_types;
 mod lib_conv_capi;
 pub(crate) mod writers;
@@ -19,6 +19,11 @@ pub type TypeFormatVersion = u32;
 // C side type: ::std::os::raw::c_uint
 pub type TypeFrames = u32;
 
+// bits in `GlobalMetaData.mask`, indicating which fields of an atom are saved in the file.
+pub const MASK_POSITION: TypeDumpMask = 1 << 0;
+pub const MASK_VELOCITY: TypeDumpMask = 1 << 1;
+pub const MASK_FORCE: TypeDumpMask = 1 << 2;
+
 #[repr(C)]
 #[derive(ByteStruct, PartialEq)]
 #[derive(Debug, Copy, Clone)]
@@ -37,8 +42,17 @@ pub struct GlobalMetaData {
     pub frames: TypeFrames,
 }
 
+impl GlobalMetaData {
+    // offset in file of the first atom block, after the global header, frame headers and local headers.
+    pub fn data_offset(&self) -> u64 {
+        self.self_size + (self.frames as u64) * self.frame_meta_size + self.mpi_ranks * self.local_size
+    }
+}
+
 #[repr(C)]
+#[derive(ByteStruct, PartialEq)]
 #[derive(Debug, Copy, Clone)]
+#[byte_struct_le]
 pub struct FrameMetaData {
     pub atoms_num: SizeT,
     pub atoms_num_hash_collision: SizeT,
@@ -2,6 +2,7 @@ use std::fs::{File, OpenOptions};
 use std::io::{Seek, SeekFrom, Write};
 use serde::{Serialize, Deserialize};
 use crate::conv::binary_types::TypeAtom;
+use crate::conv::v2_atom_types::TypeDumpMask;
 use crate::conv::writers::out_writer;
 
 // parse the origin binary file to another binary file.
@@ -60,7 +61,7 @@ impl out_writer::WriteProgress for BinOutWriter {
     }
 
     //todo return Result<>
-    fn on_start(&mut self, _output: &str) {
+    fn on_start(&mut self, _output: &str, _mask: TypeDumpMask) {
         self.output.seek(SeekFrom::Start(std::mem::size_of::<Header>() as u64)).unwrap();
     }
 
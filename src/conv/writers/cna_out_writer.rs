@@ -0,0 +1,70 @@
+// identify structure type of atoms by adaptive CNA, and write it as column `structure` by another writer.
+
+use crate::ans::cna;
+use crate::conv::binary_types::TypeAtom;
+use crate::conv::v2_atom_types::{FrameMetaData, TypeDumpMask};
+use crate::conv::writers::out_writer::WriteProgress;
+use crate::xyz::sim_box::SimBox;
+
+const STRUCTURE_COLUMN: &str = "structure";
+
+// atoms of a frame are collected, then they are passed to the inner writer with the structure type
+// after the frame is read.
+pub struct CnaOutWriter<W: WriteProgress> {
+    writer: W,
+    atoms: Vec<TypeAtom>,
+    // the simulation box specified by user, otherwise, free boundaries are used.
+    sim_box: Option<SimBox>,
+}
+
+impl<W: WriteProgress> WriteProgress for CnaOutWriter<W> {
+    fn on_atom_read(&mut self, atom: &TypeAtom) -> i32 {
+        self.atoms.push(*atom);
+        1
+    }
+
+    fn before_frame(&mut self, frame: u32, output: &str) {
+        self.atoms.clear();
+        self.writer.before_frame(frame, output);
+    }
+
+    fn after_frame(&mut self) {
+        // the simulation box is not saved in binary files, thus, free boundaries are used if it is not specified.
+        let positions: Vec<[f64; 3]> = self.atoms.iter().map(|atom| atom.atom_location).collect();
+        let structures = cna::adaptive_cna(&positions, self.sim_box);
+        for (atom, structure) in self.atoms.iter().zip(structures) {
+            self.writer.on_atom_read_with_extra(atom, &[structure.to_string()]);
+        }
+        self.writer.after_frame();
+    }
+
+    fn on_start(&mut self, output: &str, mask: TypeDumpMask) {
+        self.writer.set_extra_columns(&[STRUCTURE_COLUMN]);
+        self.writer.on_start(output, mask);
+    }
+
+    fn done(&mut self) {
+        self.writer.done();
+    }
+
+    fn check_mask(&self, mask: TypeDumpMask) -> Result<(), String> {
+        self.writer.check_mask(mask)
+    }
+
+    fn set_sim_box(&mut self, sim_box: SimBox) {
+        self.sim_box = Some(sim_box);
+        self.writer.set_sim_box(sim_box);
+    }
+
+    fn on_frame_header(&mut self, header: &FrameMetaData) {
+        self.writer.on_frame_header(header);
+    }
+
+    fn set_frame_step(&mut self, step: u64, time: Option<f64>) {
+        self.writer.set_frame_step(step, time);
+    }
+}
+
+pub fn new_writer<W: WriteProgress>(writer: W) -> CnaOutWriter<W> {
+    CnaOutWriter { writer, atoms: Vec::new(), sim_box: None }
+}
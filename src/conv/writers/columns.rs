@@ -0,0 +1,146 @@
+// Columns of atoms written by dump and text writers, selected by `--columns` option.
+
+use crate::conv::binary_types::TypeAtom;
+use crate::conv::v2_atom_types::{TypeDumpMask, MASK_FORCE, MASK_POSITION, MASK_VELOCITY};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    // atom type number
+    Type,
+    // element name of atom type
+    Element,
+    InterType,
+    X,
+    Y,
+    Z,
+    Vx,
+    Vy,
+    Vz,
+    Fx,
+    Fy,
+    Fz,
+}
+
+const COLUMNS: [Column; 13] = [Column::Id, Column::Type, Column::Element, Column::InterType, Column::X, Column::Y, Column::Z,
+    Column::Vx, Column::Vy, Column::Vz, Column::Fx, Column::Fy, Column::Fz];
+
+impl Column {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Type => "type",
+            Column::Element => "element",
+            Column::InterType => "inter_type",
+            Column::X => "x",
+            Column::Y => "y",
+            Column::Z => "z",
+            Column::Vx => "vx",
+            Column::Vy => "vy",
+            Column::Vz => "vz",
+            Column::Fx => "fx",
+            Column::Fy => "fy",
+            Column::Fz => "fz",
+        }
+    }
+
+    // the mask bit of the field in binary file, 0 if the field is always available.
+    fn mask(&self) -> TypeDumpMask {
+        match self {
+            Column::Id | Column::Type | Column::Element | Column::InterType => 0,
+            Column::X | Column::Y | Column::Z => MASK_POSITION,
+            Column::Vx | Column::Vy | Column::Vz => MASK_VELOCITY,
+            Column::Fx | Column::Fy | Column::Fz => MASK_FORCE,
+        }
+    }
+
+    fn value(&self, atom: &TypeAtom, prec: usize) -> String {
+        match self {
+            Column::Id => atom.id.to_string(),
+            Column::Type => atom.tp.to_string(),
+            Column::Element => atom.get_name_by_ele_name().to_string(),
+            Column::InterType => atom.inter_type.to_string(),
+            Column::X => format!("{:.*}", prec, atom.atom_location[0]),
+            Column::Y => format!("{:.*}", prec, atom.atom_location[1]),
+            Column::Z => format!("{:.*}", prec, atom.atom_location[2]),
+            Column::Vx => format!("{:.*}", prec, atom.atom_velocity[0]),
+            Column::Vy => format!("{:.*}", prec, atom.atom_velocity[1]),
+            Column::Vz => format!("{:.*}", prec, atom.atom_velocity[2]),
+            Column::Fx => format!("{:.*}", prec, atom.atom_force[0]),
+            Column::Fy => format!("{:.*}", prec, atom.atom_force[1]),
+            Column::Fz => format!("{:.*}", prec, atom.atom_force[2]),
+        }
+    }
+}
+
+// parse comma separated column names, e.g. `id,type,x,y,z`.
+pub fn parse_columns(spec: &str) -> Result<Vec<Column>, String> {
+    let mut columns = Vec::new();
+    for name in spec.split(',').map(|name| name.trim()) {
+        let column = match COLUMNS.iter().find(|c| c.name() == name) {
+            Some(column) => *column,
+            None => {
+                let names: Vec<&str> = COLUMNS.iter().map(|c| c.name()).collect();
+                return Err(format!("unknown column `{}`, available columns: {}", name, names.join(",")));
+            }
+        };
+        if columns.contains(&column) {
+            return Err(format!("duplicated column `{}`", name));
+        }
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+// check that the fields of all columns are saved in the input file, by the `mask` of the file.
+pub fn check_columns(columns: &[Column], mask: TypeDumpMask) -> Result<(), String> {
+    let unavailable: Vec<&str> = columns.iter().filter(|c| mask & c.mask() != c.mask()).map(|c| c.name()).collect();
+    if unavailable.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("column(s) `{}` are not available, the fields are not saved in the input file", unavailable.join(",")))
+    }
+}
+
+// all fields available in the input file.
+pub fn available_columns(mask: TypeDumpMask) -> Vec<Column> {
+    COLUMNS.iter().filter(|c| **c != Column::Type && mask & c.mask() == c.mask()).cloned().collect()
+}
+
+pub fn header(columns: &[Column], separator: &str) -> String {
+    columns.iter().map(|c| c.name()).collect::<Vec<&str>>().join(separator)
+}
+
+// values of columns, separated by " \t".
+pub fn format_atom(columns: &[Column], atom: &TypeAtom, prec: usize) -> String {
+    columns.iter().map(|c| c.value(atom, prec)).collect::<Vec<String>>().join(" \t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns() {
+        let columns = parse_columns("id, type,x,y,z,vx").unwrap();
+        assert_eq!(columns, vec![Column::Id, Column::Type, Column::X, Column::Y, Column::Z, Column::Vx]);
+        assert!(parse_columns("id,x,vel").unwrap_err().contains("unknown column `vel`"));
+        assert!(parse_columns("id,x,x").is_err());
+
+        assert!(check_columns(&columns, MASK_POSITION | MASK_VELOCITY).is_ok());
+        assert_eq!(check_columns(&[Column::Id, Column::Fx, Column::Fy], MASK_POSITION).unwrap_err(),
+                   "column(s) `fx,fy` are not available, the fields are not saved in the input file");
+        assert_eq!(header(&available_columns(MASK_POSITION | MASK_FORCE), " "), "id element inter_type x y z fx fy fz");
+
+        let atom = TypeAtom {
+            id: 3,
+            tp: 1,
+            inter_type: 0,
+            atom_location: [1.0, 2.0, 3.0],
+            atom_velocity: [0.5, 0.0, 0.0],
+            atom_force: [0.0; 3],
+        };
+        assert_eq!(format_atom(&columns, &atom, 2), "3 \t1 \t1.00 \t2.00 \t3.00 \t0.50");
+        assert_eq!(format_atom(&[Column::Element], &atom, 2), "Cu");
+    }
+}
@@ -3,42 +3,89 @@
 use std::fs::{OpenOptions, File};
 use std::io::{Seek, SeekFrom, Write};
 use crate::conv::binary_types;
+use crate::conv::v2_atom_types::{FrameMetaData, TypeDumpMask};
+use crate::conv::writers::columns::{self, Column};
 use crate::conv::writers::out_writer;
+use crate::xyz::sim_box::SimBox;
 
-const DUMP_HEADER_MAX_SIZE: usize = 256; // max header size in bytes
+const DUMP_HEADER_MAX_SIZE: usize = 512; // max header size in bytes
+const DEFAULT_COLUMNS: [Column; 5] = [Column::Id, Column::Type, Column::X, Column::Y, Column::Z];
 
 pub struct DumpOutWriter {
     output: std::io::BufWriter<File>,
     prec: usize,
+    // columns selected by user, `DEFAULT_COLUMNS` are used if it is empty.
+    columns: Vec<Column>,
+    extra_columns: Vec<&'static str>,
+    // the simulation box specified by user, otherwise, the box is determined by the bounds of atoms.
+    sim_box: Option<SimBox>,
+    // index, step and time of current frame. The frame index is used as time step if the step is unknown.
+    frame: u32,
+    step: Option<u64>,
+    time: Option<f64>,
     header_pos: u64,
     atom_count: u64,
     bound_min: (f64, f64, f64),
     bound_max: (f64, f64, f64),
 }
 
+/**
+ * `ITEM: BOX BOUNDS` of the simulation box in LAMMPS format, the cell vectors must be a = (lx, 0, 0),
+ * b = (xy, ly, 0) and c = (xz, yz, lz), otherwise, `None` is returned.
+ * For a triclinic box, the bounds are the bounding box of the triclinic box, followed by the tilt factors.
+ * see also: https://docs.lammps.org/Howto_triclinic.html
+ */
+fn box_bounds(sim_box: &SimBox) -> Option<String> {
+    let cell = sim_box.cell;
+    if cell[0][1] != 0.0 || cell[0][2] != 0.0 || cell[1][2] != 0.0 {
+        return None;
+    }
+    let flags: Vec<&str> = sim_box.pbc.iter().map(|&p| if p { "pp" } else { "ff" }).collect();
+    let origin = sim_box.origin;
+    let hi = [origin[0] + cell[0][0], origin[1] + cell[1][1], origin[2] + cell[2][2]];
+    let (xy, xz, yz) = (cell[1][0], cell[2][0], cell[2][1]);
+    if xy == 0.0 && xz == 0.0 && yz == 0.0 {
+        return Some(format!("ITEM: BOX BOUNDS {}\n{} {}\n{} {}\n{} {}", flags.join(" "),
+                            origin[0], hi[0], origin[1], hi[1], origin[2], hi[2]));
+    }
+    Some(format!("ITEM: BOX BOUNDS xy xz yz {}\n{} {} {}\n{} {} {}\n{} {} {}", flags.join(" "),
+                 origin[0] + 0.0_f64.min(xy).min(xz).min(xy + xz), hi[0] + 0.0_f64.max(xy).max(xz).max(xy + xz), xy,
+                 origin[1] + 0.0_f64.min(yz), hi[1] + 0.0_f64.max(yz), xz,
+                 origin[2], hi[2], yz))
+}
+
 impl DumpOutWriter {
-    // write header, include atom time step, number, bounds and atoms position.
+    // select the columns of atoms to be written.
+    pub fn set_columns(&mut self, columns: Vec<Column>) {
+        self.columns = columns;
+    }
+
+    // write header, include time, time step, atom number and box bounds.
     fn write_header(&mut self) {
         self.output.seek(SeekFrom::Start(self.header_pos)).unwrap();
 
-        let fmt_string = format!("ITEM: TIMESTEP\n\
-        {}\n\
-        ITEM: NUMBER OF ATOMS\n\
-        {}\n\
-        ITEM: BOX BOUNDS pp pp pp\n\
-        {} {} {}\n{} {} {}", 0, self.atom_count,
-                                 self.bound_min.0 - 1e-4, self.bound_min.1 - 1e-4, self.bound_min.2 - 1e-4,
-                                 self.bound_max.0 + 1e-4, self.bound_max.0 + 1e-4, self.bound_max.0 + 1e-4);
-        let written_size = self.output.write(fmt_string.as_bytes()).unwrap(); // size of data of atom count
+        let bounds = match self.sim_box.as_ref().and_then(box_bounds) {
+            Some(bounds) => bounds,
+            // box is determined by the bounds of atoms.
+            None => format!("ITEM: BOX BOUNDS pp pp pp\n{} {}\n{} {}\n{} {}",
+                            self.bound_min.0 - 1e-4, self.bound_max.0 + 1e-4,
+                            self.bound_min.1 - 1e-4, self.bound_max.1 + 1e-4,
+                            self.bound_min.2 - 1e-4, self.bound_max.2 + 1e-4),
+        };
+        let time = self.time.map_or(String::new(), |time| format!("ITEM: TIME\n{}\n", time));
+        let fmt_string = format!("{}ITEM: TIMESTEP\n{}\nITEM: NUMBER OF ATOMS\n{}\n{}",
+                                 time, self.step.unwrap_or(self.frame as u64), self.atom_count, bounds);
+        self.output.write_all(fmt_string.as_bytes()).unwrap();
+        let written_size = fmt_string.len();
 
         if written_size >= DUMP_HEADER_MAX_SIZE {
-            panic!("atoms count too big");
+            panic!("dump header too long");
         } else {
             // fill the gap.
             let left_size = DUMP_HEADER_MAX_SIZE - written_size;
-            let mut buf: Vec<u8> = vec![' ' as u8; left_size];
-            buf[left_size - 1] = '\n' as u8;
-            self.output.write(buf.as_slice()).unwrap();
+            let mut buf: Vec<u8> = vec![b' '; left_size];
+            buf[left_size - 1] = b'\n';
+            self.output.write_all(buf.as_slice()).unwrap();
         }
     }
     fn position(&mut self) -> u64 {
@@ -51,11 +98,12 @@ impl DumpOutWriter {
 // Then, it will seek and write the header.
 impl out_writer::WriteProgress for DumpOutWriter {
     fn on_atom_read(&mut self, atom: &binary_types::TypeAtom) -> i32 {
-        let fmt_string = format!("{} {} \t{:.*} \t{:.*} \t{:.*}\n",
-                                 atom.id, atom.tp,
-                                 self.prec, atom.atom_location[0],
-                                 self.prec, atom.atom_location[1],
-                                 self.prec, atom.atom_location[2]);
+        self.on_atom_read_with_extra(atom, &[])
+    }
+
+    fn on_atom_read_with_extra(&mut self, atom: &binary_types::TypeAtom, extra: &[String]) -> i32 {
+        let fmt_string = format!("{}{}\n", columns::format_atom(&self.columns, atom, self.prec),
+                                 out_writer::extra_columns_string(extra));
         self.output.write(fmt_string.as_bytes()).unwrap();
         // re-calculate bound
         if atom.atom_location[0] < self.bound_min.0 {
@@ -81,11 +129,17 @@ impl out_writer::WriteProgress for DumpOutWriter {
         return 1 as i32;
     }
 
-    fn before_frame(&mut self, _frame: u32, _output_file: &str) {
+    fn before_frame(&mut self, frame: u32, _output_file: &str) {
+        self.frame = frame;
+        self.step = None;
+        self.time = None;
         self.atom_count = 0;
+        self.bound_min = (f64::MAX, f64::MAX, f64::MAX);
+        self.bound_max = (f64::MIN, f64::MIN, f64::MIN);
         self.header_pos = self.position();
         self.output.seek(SeekFrom::Current(DUMP_HEADER_MAX_SIZE as i64)).unwrap();
-        self.output.write("ITEM: ATOMS id type x y z\n".as_bytes()).unwrap();
+        let extra_columns: String = self.extra_columns.iter().map(|c| format!(" {}", c)).collect();
+        self.output.write_all(format!("ITEM: ATOMS {}{}\n", columns::header(&self.columns, " "), extra_columns).as_bytes()).unwrap();
     }
 
     fn after_frame(&mut self) {
@@ -95,7 +149,10 @@ impl out_writer::WriteProgress for DumpOutWriter {
         self.output.seek(SeekFrom::Start(bytes_to_end_frame)).unwrap();
     }
 
-    fn on_start(&mut self, _output: &str) {
+    fn on_start(&mut self, _output: &str, _mask: TypeDumpMask) {
+        if self.columns.is_empty() {
+            self.columns = DEFAULT_COLUMNS.to_vec();
+        }
         // append to end of file.
         // we dont use append mode to write, because in this mode,
         // it always reposition cursor to end of file before each write.
@@ -104,16 +161,51 @@ impl out_writer::WriteProgress for DumpOutWriter {
     }
 
     fn done(&mut self) {}
+
+    fn set_extra_columns(&mut self, columns: &[&'static str]) {
+        self.extra_columns = columns.to_vec();
+    }
+
+    fn check_mask(&self, mask: TypeDumpMask) -> Result<(), String> {
+        columns::check_columns(&self.columns, mask)
+    }
+
+    fn set_sim_box(&mut self, sim_box: SimBox) {
+        if box_bounds(&sim_box).is_none() {
+            println!("Warning: the box can not be written to dump file (cell vectors a, b and c must be (lx 0 0), (xy ly 0) and (xz yz lz)), \
+            the box is determined by the bounds of atoms.");
+        }
+        self.sim_box = Some(sim_box);
+    }
+
+    fn on_frame_header(&mut self, header: &FrameMetaData) {
+        self.set_frame_step(header.step, Some(header.time));
+    }
+
+    fn set_frame_step(&mut self, step: u64, time: Option<f64>) {
+        self.step = Some(step);
+        self.time = time;
+    }
 }
 
+// frames are appended to the output file, thus, frames of multiple input files can be written to the same file.
 pub fn new_writer(filename: &str, precision: u32) -> DumpOutWriter {
+    open_writer(filename, precision, false)
+}
+
+// the output file is truncated when it is opened, frames written by previous runs are not kept.
+pub fn new_truncated_writer(filename: &str, precision: u32) -> DumpOutWriter {
+    open_writer(filename, precision, true)
+}
+
+fn open_writer(filename: &str, precision: u32, truncate: bool) -> DumpOutWriter {
     // open output  file for writing.
     let file = OpenOptions::new()
         .read(false)
         .write(true)
         .create(true)
         .append(false)
-        .truncate(false)
+        .truncate(truncate)
         .open(filename);
 
     match file {
@@ -121,6 +213,12 @@ pub fn new_writer(filename: &str, precision: u32) -> DumpOutWriter {
             return DumpOutWriter {
                 output: std::io::BufWriter::with_capacity(1024 * 1024, stream),
                 prec: precision as usize,
+                columns: Vec::new(),
+                extra_columns: Vec::new(),
+                sim_box: None,
+                frame: 0,
+                step: None,
+                time: None,
                 header_pos: 0,
                 atom_count: 0,
                 bound_min: (f64::MAX, f64::MAX, f64::MAX),
@@ -132,3 +230,26 @@ pub fn new_writer(filename: &str, precision: u32) -> DumpOutWriter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xyz::dump_reader::DumpReader;
+
+    #[test]
+    fn test_box_bounds() {
+        let sim_box = SimBox::orthogonal((10.0, 20.0, 30.5), [-1.0, 0.0, 0.5]).unwrap();
+        assert_eq!(box_bounds(&sim_box).unwrap(), "ITEM: BOX BOUNDS pp pp pp\n-1 9\n0 20\n0.5 31");
+
+        let sim_box = SimBox::new([[10.0, 0.0, 0.0], [2.0, 10.0, 0.0], [-1.0, 0.0, 10.0]], [0.0; 3], [true, true, false]).unwrap();
+        let bounds = box_bounds(&sim_box).unwrap();
+        assert_eq!(bounds, "ITEM: BOX BOUNDS xy xz yz pp pp ff\n-1 12 2\n0 10 -1\n0 10 0");
+        // the box can be read back.
+        let dump = format!("ITEM: TIMESTEP\n0\nITEM: NUMBER OF ATOMS\n0\n{}\nITEM: ATOMS id type x y z\n", bounds);
+        let frame = DumpReader::new(dump.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(frame.sim_box, Some(sim_box));
+
+        let sim_box = SimBox::new([[10.0, 1.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]], [0.0; 3], [true; 3]).unwrap();
+        assert_eq!(box_bounds(&sim_box), None);
+    }
+}
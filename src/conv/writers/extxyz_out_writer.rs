@@ -0,0 +1,217 @@
+// convert md binary result to extended xyz format (used by OVITO, ASE and so on).
+// see also: https://github.com/libAtoms/extxyz
+
+use std::io::{Write, SeekFrom};
+use std::io::Seek;
+use std::fs::{OpenOptions, File};
+use crate::conv::binary_types;
+use crate::conv::v2_atom_types::{TypeDumpMask, MASK_FORCE, MASK_VELOCITY};
+use crate::conv::writers::out_writer;
+use crate::xyz::sim_box::SimBox;
+
+const EXTXYZ_HEADER_MAX_SIZE: usize = 512; // max header size (atom count line and comment line) in bytes
+
+pub struct ExtXYZOutWriter {
+    output: std::io::BufWriter<File>,
+    prec: usize,
+    mask: TypeDumpMask,
+    extra_columns: Vec<&'static str>,
+    // the simulation box specified by user, otherwise, the box is determined by the bounds of atoms.
+    sim_box: Option<SimBox>,
+    header_pos: u64,
+    atom_count: u64,
+    bound_min: (f64, f64, f64),
+    bound_max: (f64, f64, f64),
+}
+
+// generate the value of `Properties` in comment line, based on the fields available in the input file
+// and the extra columns (as string columns). The columns order is the same as the order of written atoms.
+fn properties(mask: TypeDumpMask, extra_columns: &[&str]) -> String {
+    let mut props = String::from("species:S:1:pos:R:3");
+    if mask & MASK_VELOCITY != 0 {
+        props.push_str(":velo:R:3");
+    }
+    if mask & MASK_FORCE != 0 {
+        props.push_str(":forces:R:3");
+    }
+    props.push_str(":id:I:1");
+    for column in extra_columns {
+        props.push_str(&format!(":{}:S:1", column));
+    }
+    props
+}
+
+// generate the comment line of extxyz file, the box is given by cell vectors, origin and periodic flags.
+fn comment_line(mask: TypeDumpMask, extra_columns: &[&str], cell: [[f64; 3]; 3], origin: [f64; 3], pbc: [bool; 3]) -> String {
+    let lattice: Vec<String> = cell.iter().flat_map(|v| v.iter()).map(|x| x.to_string()).collect();
+    let pbc: Vec<&str> = pbc.iter().map(|&p| if p { "T" } else { "F" }).collect();
+    format!("Lattice=\"{}\" Origin=\"{} {} {}\" Properties={} pbc=\"{}\"",
+            lattice.join(" "), origin[0], origin[1], origin[2],
+            properties(mask, extra_columns), pbc.join(" "))
+}
+
+impl ExtXYZOutWriter {
+    // write header, include atom number, box bounds and properties.
+    fn write_header(&mut self) {
+        self.output.seek(SeekFrom::Start(self.header_pos)).unwrap();
+
+        let comment = match self.sim_box {
+            Some(sim_box) => comment_line(self.mask, &self.extra_columns, sim_box.cell, sim_box.origin, sim_box.pbc),
+            // the box is unknown, the bounds of atoms are written with free boundaries.
+            None => {
+                let origin = [self.bound_min.0 - 1e-4, self.bound_min.1 - 1e-4, self.bound_min.2 - 1e-4];
+                let cell = [[self.bound_max.0 - self.bound_min.0 + 2e-4, 0.0, 0.0],
+                    [0.0, self.bound_max.1 - self.bound_min.1 + 2e-4, 0.0],
+                    [0.0, 0.0, self.bound_max.2 - self.bound_min.2 + 2e-4]];
+                comment_line(self.mask, &self.extra_columns, cell, origin, [false; 3])
+            }
+        };
+        let fmt_string = format!("{}\n{}", self.atom_count, comment);
+        self.output.write_all(fmt_string.as_bytes()).unwrap();
+        let written_size = fmt_string.len();
+
+        if written_size >= EXTXYZ_HEADER_MAX_SIZE {
+            panic!("extxyz header too long");
+        } else {
+            // fill the gap of comment line.
+            let left_size = EXTXYZ_HEADER_MAX_SIZE - written_size;
+            let mut buf: Vec<u8> = vec![b' '; left_size];
+            buf[left_size - 1] = b'\n';
+            self.output.write_all(buf.as_slice()).unwrap();
+        }
+    }
+
+    fn position(&mut self) -> u64 {
+        self.output.stream_position().unwrap()
+    }
+
+    fn update_bound(&mut self, location: &[f64; 3]) {
+        self.bound_min.0 = self.bound_min.0.min(location[0]);
+        self.bound_min.1 = self.bound_min.1.min(location[1]);
+        self.bound_min.2 = self.bound_min.2.min(location[2]);
+        self.bound_max.0 = self.bound_max.0.max(location[0]);
+        self.bound_max.1 = self.bound_max.1.max(location[1]);
+        self.bound_max.2 = self.bound_max.2.max(location[2]);
+    }
+}
+
+// Just like dump writer, we write atoms data of a frame first,
+// then seek back and write the header of this frame.
+impl out_writer::WriteProgress for ExtXYZOutWriter {
+    fn on_atom_read(&mut self, atom: &binary_types::TypeAtom) -> i32 {
+        self.on_atom_read_with_extra(atom, &[])
+    }
+
+    fn on_atom_read_with_extra(&mut self, atom: &binary_types::TypeAtom, extra: &[String]) -> i32 {
+        let mut fmt_string = format!("{} \t{:.*} \t{:.*} \t{:.*}",
+                                     atom.get_name_by_ele_name(),
+                                     self.prec, atom.atom_location[0],
+                                     self.prec, atom.atom_location[1],
+                                     self.prec, atom.atom_location[2]);
+        if self.mask & MASK_VELOCITY != 0 {
+            fmt_string.push_str(format!(" \t{:.*} \t{:.*} \t{:.*}",
+                                        self.prec, atom.atom_velocity[0],
+                                        self.prec, atom.atom_velocity[1],
+                                        self.prec, atom.atom_velocity[2]).as_str());
+        }
+        if self.mask & MASK_FORCE != 0 {
+            fmt_string.push_str(format!(" \t{:.*} \t{:.*} \t{:.*}",
+                                        self.prec, atom.atom_force[0],
+                                        self.prec, atom.atom_force[1],
+                                        self.prec, atom.atom_force[2]).as_str());
+        }
+        fmt_string.push_str(format!(" \t{}{}\n", atom.id, out_writer::extra_columns_string(extra)).as_str());
+        self.output.write_all(fmt_string.as_bytes()).unwrap();
+
+        self.update_bound(&atom.atom_location);
+        self.atom_count += 1;
+        1
+    }
+
+    fn before_frame(&mut self, _frame: u32, _output: &str) {
+        self.atom_count = 0;
+        self.bound_min = (f64::MAX, f64::MAX, f64::MAX);
+        self.bound_max = (f64::MIN, f64::MIN, f64::MIN);
+        self.header_pos = self.position();
+        self.output.seek(SeekFrom::Current(EXTXYZ_HEADER_MAX_SIZE as i64)).unwrap();
+    }
+
+    fn after_frame(&mut self) {
+        let bytes_to_end_frame: u64 = self.position();
+        self.write_header();
+        // seek back for processing out file with multiple frames.
+        self.output.seek(SeekFrom::Start(bytes_to_end_frame)).unwrap();
+    }
+
+    fn on_start(&mut self, _output: &str, mask: TypeDumpMask) {
+        self.mask = mask;
+    }
+
+    fn done(&mut self) {}
+
+    fn set_extra_columns(&mut self, columns: &[&'static str]) {
+        self.extra_columns = columns.to_vec();
+    }
+
+    fn set_sim_box(&mut self, sim_box: SimBox) {
+        self.sim_box = Some(sim_box);
+    }
+}
+
+// filename: output file.
+pub fn new_writer(filename: &str, precision: u32) -> ExtXYZOutWriter {
+    // open output file for writing.
+    let file = OpenOptions::new()
+        .read(false)
+        .write(true)
+        .create(true)
+        .append(false)
+        .truncate(true)
+        .open(filename);
+
+    match file {
+        Ok(stream) => {
+            ExtXYZOutWriter {
+                output: std::io::BufWriter::with_capacity(1024 * 1024, stream),
+                prec: precision as usize,
+                mask: 0,
+                extra_columns: Vec::new(),
+                sim_box: None,
+                header_pos: 0,
+                atom_count: 0,
+                bound_min: (f64::MAX, f64::MAX, f64::MAX),
+                bound_max: (f64::MIN, f64::MIN, f64::MIN),
+            }
+        }
+        Err(err) => {
+            panic!("{:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conv::v2_atom_types::MASK_POSITION;
+
+    #[test]
+    fn test_properties() {
+        assert_eq!(properties(MASK_POSITION, &[]), "species:S:1:pos:R:3:id:I:1");
+        assert_eq!(properties(MASK_POSITION | MASK_VELOCITY, &[]), "species:S:1:pos:R:3:velo:R:3:id:I:1");
+        assert_eq!(properties(MASK_POSITION, &["structure"]), "species:S:1:pos:R:3:id:I:1:structure:S:1");
+        assert_eq!(properties(MASK_POSITION | MASK_VELOCITY | MASK_FORCE, &[]),
+                   "species:S:1:pos:R:3:velo:R:3:forces:R:3:id:I:1");
+    }
+
+    #[test]
+    fn test_comment_line() {
+        let cell = [[10.0, 0.0, 0.0], [0.0, 20.0, 0.0], [0.0, 0.0, 30.5]];
+        let line = comment_line(MASK_POSITION | MASK_FORCE, &[], cell, [-1.0, 0.0, 0.5], [true, true, false]);
+        assert_eq!(line, "Lattice=\"10 0 0 0 20 0 0 0 30.5\" Origin=\"-1 0 0.5\" \
+        Properties=species:S:1:pos:R:3:forces:R:3:id:I:1 pbc=\"T T F\"");
+
+        // the box written is read back by the box parser of extxyz comment line.
+        let sim_box = SimBox::from_extxyz_comment(&line).unwrap();
+        assert_eq!((sim_box.cell, sim_box.origin, sim_box.pbc), (cell, [-1.0, 0.0, 0.5], [true, true, false]));
+    }
+}
@@ -1,5 +1,8 @@
 pub(crate) mod out_writer;
+pub(crate) mod columns;
 pub(crate) mod xyz_out_writer;
+pub(crate) mod extxyz_out_writer;
 pub(crate) mod text_out_writer;
 pub(crate) mod bin_out_writer;
 pub(crate) mod dump_out_writer;
+pub(crate) mod cna_out_writer;
@@ -1,4 +1,6 @@
 use crate::conv::binary_types;
+use crate::conv::v2_atom_types::{FrameMetaData, TypeDumpMask};
+use crate::xyz::sim_box::SimBox;
 
 pub trait WriteProgress {
     // If one atom is read from binary file, this function will be called.
@@ -7,8 +9,32 @@ pub trait WriteProgress {
     fn before_frame(&mut self, frame: u32, output: &str);
     // after reading a new frame (after reading atoms for next time step)
     fn after_frame(&mut self);
-    // called before writing
-    fn on_start(&mut self, output: &str);
+    // called before writing, `mask` tells which fields of atoms are available in the input file.
+    fn on_start(&mut self, output: &str, mask: TypeDumpMask);
     // called after all finished
     fn done(&mut self);
+    // names of extra columns (e.g. `structure` identified by CNA) appended to each atom, called before `on_start`.
+    // The extra columns are ignored by default.
+    fn set_extra_columns(&mut self, _columns: &[&'static str]) {}
+    // like `on_atom_read`, with values of the extra columns.
+    fn on_atom_read_with_extra(&mut self, atom: &binary_types::TypeAtom, _extra: &[String]) -> i32 {
+        self.on_atom_read(atom)
+    }
+    // check the fields required by the writer are saved in the input file (by `mask`), called before `on_start`.
+    fn check_mask(&self, _mask: TypeDumpMask) -> Result<(), String> {
+        Ok(())
+    }
+    // the simulation box specified by user, called before `on_start`. It is ignored by default.
+    fn set_sim_box(&mut self, _sim_box: SimBox) {}
+    // header (step, time and atoms number) of current frame, called after `before_frame` if the file has frame headers.
+    // It is ignored by default.
+    fn on_frame_header(&mut self, _header: &FrameMetaData) {}
+    // step and time (if known) of current frame read from other formats (e.g. dump files without time),
+    // called after `before_frame`. It is ignored by default.
+    fn set_frame_step(&mut self, _step: u64, _time: Option<f64>) {}
+}
+
+// format values of extra columns, each value is led by a separator.
+pub fn extra_columns_string(extra: &[String]) -> String {
+    extra.iter().map(|value| format!(" \t{}", value)).collect()
 }
@@ -1,11 +1,23 @@
 use std::fs::{OpenOptions, File};
 use std::io::Write;
 use crate::conv::binary_types::TypeAtom;
+use crate::conv::v2_atom_types::TypeDumpMask;
+use crate::conv::writers::columns::{self, Column};
 use crate::conv::writers::out_writer;
 
 pub struct TextOutWriter {
     output: std::io::BufWriter<File>,
     prec: usize,
+    // columns selected by user, all fields available in the input file are written if it is empty.
+    columns: Vec<Column>,
+    extra_columns: Vec<&'static str>,
+}
+
+impl TextOutWriter {
+    // select the columns of atoms to be written.
+    pub fn set_columns(&mut self, columns: Vec<Column>) {
+        self.columns = columns;
+    }
 }
 
 /**
@@ -16,18 +28,13 @@ writer.write_all(b"test\n");
  */
 impl out_writer::WriteProgress for TextOutWriter {
     fn on_atom_read(&mut self, atom: &TypeAtom) -> i32 {
-        let fmt_string = format!("{} \t {} \t{} \t{:.*} \t{:.*} \t{:.*} \t{:.*} \t{:.*} \t{:.*}\t{:.*} \t{:.*} \t{:.*}\n",
-                                 atom.id, atom.get_name_by_ele_name(), atom.inter_type,
-                                 self.prec, atom.atom_location[0],
-                                 self.prec, atom.atom_location[1],
-                                 self.prec, atom.atom_location[2],
-                                 self.prec, atom.atom_velocity[0],
-                                 self.prec, atom.atom_velocity[1],
-                                 self.prec, atom.atom_velocity[2],
-                                 self.prec, atom.atom_force[0],
-                                 self.prec, atom.atom_force[1],
-                                 self.prec, atom.atom_force[2]);
-        self.output.write(fmt_string.as_bytes()).unwrap();
+        self.on_atom_read_with_extra(atom, &[])
+    }
+
+    fn on_atom_read_with_extra(&mut self, atom: &TypeAtom, extra: &[String]) -> i32 {
+        let fmt_string = format!("{}{}\n", columns::format_atom(&self.columns, atom, self.prec),
+                                 out_writer::extra_columns_string(extra));
+        self.output.write_all(fmt_string.as_bytes()).unwrap();
         return 1 as i32;
     }
 
@@ -36,13 +43,25 @@ impl out_writer::WriteProgress for TextOutWriter {
     fn after_frame(&mut self) {}
 
     //todo return Result<>
-    fn on_start(&mut self, _output: &str) {
+    fn on_start(&mut self, _output: &str, mask: TypeDumpMask) {
+        if self.columns.is_empty() {
+            self.columns = columns::available_columns(mask);
+        }
         // write header.
-        self.output.write(b"id \tstep \ttype \tinter_type \tlocate.x \tlocate.y \tlocate.z \tv.x \tv.y \tv.z\n").unwrap();
+        let extra_columns: String = self.extra_columns.iter().map(|c| format!(" \t{}", c)).collect();
+        self.output.write_all(format!("{}{}\n", columns::header(&self.columns, " \t"), extra_columns).as_bytes()).unwrap();
     }
 
     //todo return Result<>
     fn done(&mut self) {}
+
+    fn check_mask(&self, mask: TypeDumpMask) -> Result<(), String> {
+        columns::check_columns(&self.columns, mask)
+    }
+
+    fn set_extra_columns(&mut self, columns: &[&'static str]) {
+        self.extra_columns = columns.to_vec();
+    }
 }
 
 // filename: output file.
@@ -60,6 +79,8 @@ pub fn new_writer(filename: &str, precision: u32) -> TextOutWriter {
             return TextOutWriter {
                 output: std::io::BufWriter::with_capacity(1024 * 1024, stream),
                 prec: precision as usize,
+                columns: Vec::new(),
+                extra_columns: Vec::new(),
             };
         }
         Err(err) => {
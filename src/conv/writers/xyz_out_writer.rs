@@ -2,6 +2,7 @@ use std::io::{Write, SeekFrom};
 use std::io::Seek;
 use std::fs::{OpenOptions, File};
 use crate::conv::binary_types;
+use crate::conv::v2_atom_types::TypeDumpMask;
 use crate::conv::writers::out_writer;
 
 pub struct XYZOutWriter {
@@ -29,11 +30,16 @@ impl XYZOutWriter {
 
 impl out_writer::WriteProgress for XYZOutWriter {
     fn on_atom_read(&mut self, atom: &binary_types::TypeAtom) -> i32 {
-        let fmt_string = format!("{} \t{:.*} \t{:.*} \t{:.*}\n",
+        self.on_atom_read_with_extra(atom, &[])
+    }
+
+    fn on_atom_read_with_extra(&mut self, atom: &binary_types::TypeAtom, extra: &[String]) -> i32 {
+        let fmt_string = format!("{} \t{:.*} \t{:.*} \t{:.*}{}\n",
                                  atom.get_name_by_ele_name(),
                                  self.prec, atom.atom_location[0],
                                  self.prec, atom.atom_location[1],
-                                 self.prec, atom.atom_location[2]);
+                                 self.prec, atom.atom_location[2],
+                                 out_writer::extra_columns_string(extra));
         self.output.write(fmt_string.as_bytes()).unwrap();
         self.atom_count += 1;
         return 1 as i32;
@@ -48,7 +54,7 @@ impl out_writer::WriteProgress for XYZOutWriter {
         self.write_auto_header();
     }
 
-    fn on_start(&mut self, _output: &str) {
+    fn on_start(&mut self, _output: &str, _mask: TypeDumpMask) {
     }
 
     fn done(&mut self) {}
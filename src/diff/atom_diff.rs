@@ -0,0 +1,280 @@
+// Comparison of two atoms (with the same id) field by field.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::cli::ErrorMode;
+use crate::conv::binary_types::TypeAtom;
+use crate::conv::v2_atom_types::{TypeDumpMask, MASK_FORCE, MASK_POSITION, MASK_VELOCITY};
+use crate::xyz::particle::Particle;
+use crate::xyz::sim_box::SimBox;
+
+const POSITION_FIELDS: [&str; 3] = ["x", "y", "z"];
+const VELOCITY_FIELDS: [&str; 3] = ["vx", "vy", "vz"];
+const FORCE_FIELDS: [&str; 3] = ["fx", "fy", "fz"];
+
+// groups of fields with the same unit, deviations are tracked for each group separately.
+pub const POSITION: &str = "position";
+pub const VELOCITY: &str = "velocity";
+pub const FORCE: &str = "force";
+pub const OTHER: &str = "other";
+
+// atom that can be compared by `diff`, atoms in two files are matched by id.
+pub trait DiffAtom {
+    fn id(&self) -> u64;
+    // description of the atom, used for printing mismatched atoms.
+    fn describe(&self) -> String;
+}
+
+impl DiffAtom for Particle {
+    fn id(&self) -> u64 {
+        self.id as u64
+    }
+
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl DiffAtom for TypeAtom {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn describe(&self) -> String {
+        format!("id: {}, type: {}, position: {:?}, velocity: {:?}, force: {:?}",
+                self.id, self.get_name_by_ele_name(), self.atom_location, self.atom_velocity, self.atom_force)
+    }
+}
+
+// max error of each field when comparing two atoms.
+#[derive(Copy, Clone, Debug)]
+pub struct Tolerance {
+    pub position: f64,
+    pub velocity: f64,
+    pub force: f64,
+    // tolerance of other extra data in xyz file (data after velocity and force).
+    pub other: f64,
+    // the tolerances are absolute errors or relative errors.
+    pub mode: ErrorMode,
+}
+
+// a component of a field (e.g. `vx`) whose deviation is not less than the tolerance.
+pub struct FieldMismatch {
+    pub field: String,
+    pub value1: f64,
+    pub value2: f64,
+    pub deviation: f64,
+}
+
+// result of comparing two atoms.
+pub struct AtomDiff {
+    // max deviation of components of each compared field group (e.g. `position`).
+    pub deviations: BTreeMap<&'static str, f64>,
+    pub mismatches: Vec<FieldMismatch>,
+}
+
+impl fmt::Display for FieldMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} vs {} (deviation: {:e})", self.field, self.value1, self.value2, self.deviation)
+    }
+}
+
+impl AtomDiff {
+    pub fn new() -> Self {
+        AtomDiff { deviations: BTreeMap::new(), mismatches: Vec::new() }
+    }
+
+    pub fn is_mismatch(&self) -> bool {
+        !self.mismatches.is_empty()
+    }
+
+    // compare a field of `group` component by component. `names` are names of the components.
+    fn compare(&mut self, group: &'static str, names: &[&str], a: &[f64], b: &[f64], tolerance: f64, mode: ErrorMode) {
+        let deviations: Vec<f64> = (0..names.len()).map(|d| (a[d] - b[d]).abs()).collect();
+        let max = self.compare_deviation(names, a, b, &deviations, tolerance, mode);
+        self.record_deviation(group, max);
+    }
+
+    // compare positions, if `sim_box` is specified, the deviation is computed under minimum image convention.
+    fn compare_position(&mut self, a: &[f64], b: &[f64], tolerance: f64, mode: ErrorMode, sim_box: Option<&SimBox>) {
+        match sim_box {
+            Some(sim_box) => {
+                let d = sim_box.minimum_image([a[0] - b[0], a[1] - b[1], a[2] - b[2]]);
+                let deviations: Vec<f64> = d.iter().map(|v| v.abs()).collect();
+                let max = self.compare_deviation(&POSITION_FIELDS, a, b, &deviations, tolerance, mode);
+                self.record_deviation(POSITION, max);
+            }
+            None => self.compare(POSITION, &POSITION_FIELDS, a, b, tolerance, mode),
+        }
+    }
+
+    // max deviation of a field group, `None` if there are no components compared.
+    fn record_deviation(&mut self, group: &'static str, max: Option<f64>) {
+        if let Some(max) = max {
+            let group_max = self.deviations.entry(group).or_insert(0.0);
+            *group_max = group_max.max(max);
+        }
+    }
+
+    // compare components by deviations, and return the max deviation of the components.
+    fn compare_deviation(&mut self, names: &[&str], a: &[f64], b: &[f64], deviations: &[f64], tolerance: f64, mode: ErrorMode)
+                         -> Option<f64> {
+        let mut max: Option<f64> = None;
+        for d in 0..names.len() {
+            let mut deviation = deviations[d];
+            if mode == ErrorMode::Relative {
+                deviation = relative_error(deviation, a[d], b[d]);
+            }
+            max = Some(max.map_or(deviation, |m| m.max(deviation)));
+            if deviation >= tolerance {
+                self.mismatches.push(FieldMismatch { field: names[d].to_string(), value1: a[d], value2: b[d], deviation });
+            }
+        }
+        max
+    }
+}
+
+// relative error: |a-b|/max(|a|,|b|), where `deviation` is |a-b|.
+// If both values are 0, the relative error is 0.
+fn relative_error(deviation: f64, a: f64, b: f64) -> f64 {
+    let base = a.abs().max(b.abs());
+    if base == 0.0 {
+        0.0
+    } else {
+        deviation / base
+    }
+}
+
+/**
+ * compare two atoms from xyz files.
+ * The extra data of a particle are considered as velocity (the first 3 values), force (the next 3 values)
+ * and other data (the left values).
+ */
+pub fn compare_particle(a: &Particle, b: &Particle, tolerance: &Tolerance, sim_box: Option<&SimBox>) -> AtomDiff {
+    let mut atom_diff = AtomDiff::new();
+    atom_diff.compare_position(&[a.pos.0, a.pos.1, a.pos.2], &[b.pos.0, b.pos.1, b.pos.2],
+                               tolerance.position, tolerance.mode, sim_box);
+
+    let (extra1, extra2) = (&a.extra_data, &b.extra_data);
+    if extra1.len() != extra2.len() {
+        // the extra data can not be compared, it is a mismatch without deviation of any field group.
+        atom_diff.mismatches.push(FieldMismatch {
+            field: String::from("extra_data_len"),
+            value1: extra1.len() as f64,
+            value2: extra2.len() as f64,
+            deviation: (extra1.len() as f64 - extra2.len() as f64).abs(),
+        });
+        return atom_diff;
+    }
+    let velocity_end = std::cmp::min(3, extra1.len());
+    let force_end = std::cmp::min(6, extra1.len());
+    atom_diff.compare(VELOCITY, &VELOCITY_FIELDS[..velocity_end], &extra1[..velocity_end], &extra2[..velocity_end],
+                      tolerance.velocity, tolerance.mode);
+    atom_diff.compare(FORCE, &FORCE_FIELDS[..force_end - velocity_end], &extra1[velocity_end..force_end], &extra2[velocity_end..force_end],
+                      tolerance.force, tolerance.mode);
+    let other_names: Vec<String> = (force_end..extra1.len()).map(|i| format!("extra{}", i)).collect();
+    let other_names: Vec<&str> = other_names.iter().map(|name| name.as_str()).collect();
+    atom_diff.compare(OTHER, &other_names, &extra1[force_end..], &extra2[force_end..], tolerance.other, tolerance.mode);
+    atom_diff
+}
+
+// compare two atoms from binary files, only the fields in `mask` are compared.
+pub fn compare_type_atom(a: &TypeAtom, b: &TypeAtom, tolerance: &Tolerance, mask: TypeDumpMask,
+                         sim_box: Option<&SimBox>) -> AtomDiff {
+    let mut atom_diff = AtomDiff::new();
+    if mask & MASK_POSITION != 0 {
+        atom_diff.compare_position(&a.atom_location, &b.atom_location, tolerance.position, tolerance.mode, sim_box);
+    }
+    if mask & MASK_VELOCITY != 0 {
+        atom_diff.compare(VELOCITY, &VELOCITY_FIELDS, &a.atom_velocity, &b.atom_velocity, tolerance.velocity, tolerance.mode);
+    }
+    if mask & MASK_FORCE != 0 {
+        atom_diff.compare(FORCE, &FORCE_FIELDS, &a.atom_force, &b.atom_force, tolerance.force, tolerance.mode);
+    }
+    atom_diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_atom(location: [f64; 3], velocity: [f64; 3], force: [f64; 3]) -> TypeAtom {
+        TypeAtom { id: 1, tp: 0, inter_type: 0, atom_location: location, atom_velocity: velocity, atom_force: force }
+    }
+
+    #[test]
+    fn test_compare_type_atom() {
+        let tolerance = Tolerance { position: 1e-4, velocity: 1e-2, force: 1.0, other: 1e-4, mode: ErrorMode::Absolute };
+        let a = make_atom([1.0, 2.0, 3.0], [0.1, 0.2, 0.3], [10.0, 20.0, 30.0]);
+        let b = make_atom([1.0, 2.0, 3.00001], [0.1, 0.205, 0.3], [10.5, 20.0, 32.0]);
+
+        let all = MASK_POSITION | MASK_VELOCITY | MASK_FORCE;
+        let atom_diff = compare_type_atom(&a, &b, &tolerance, all, None);
+        assert_eq!(atom_diff.mismatches.len(), 1);
+        assert_eq!(atom_diff.mismatches[0].field, "fz");
+        assert!((atom_diff.deviations[FORCE] - 2.0).abs() < 1e-9);
+        assert!((atom_diff.deviations[POSITION] - 1e-5).abs() < 1e-9);
+
+        // force is not saved in file.
+        let atom_diff = compare_type_atom(&a, &b, &tolerance, MASK_POSITION | MASK_VELOCITY, None);
+        assert!(!atom_diff.is_mismatch());
+    }
+
+    #[test]
+    fn test_compare_with_pbc() {
+        let tolerance = Tolerance { position: 1e-4, velocity: 1e-4, force: 1e-4, other: 1e-4, mode: ErrorMode::Absolute };
+        let a = make_atom([0.00001, 2.0, 3.0], [0.0; 3], [0.0; 3]);
+        let b = make_atom([9.99999, 2.0, 3.0], [0.0; 3], [0.0; 3]);
+        assert!(compare_type_atom(&a, &b, &tolerance, MASK_POSITION, None).is_mismatch());
+        let sim_box = SimBox::orthogonal((10.0, 10.0, 10.0), [0.0; 3]).unwrap();
+        assert!(!compare_type_atom(&a, &b, &tolerance, MASK_POSITION, Some(&sim_box)).is_mismatch());
+
+        // triclinic box: the two atoms are the images of each other by cell vector b.
+        let sim_box = SimBox::new([[10.0, 0.0, 0.0], [5.0, 10.0, 0.0], [0.0, 0.0, 10.0]], [0.0; 3], [true; 3]).unwrap();
+        let c = make_atom([5.00001, 12.0, 3.0], [0.0; 3], [0.0; 3]);
+        let a = make_atom([0.0, 2.0, 3.0], [0.0; 3], [0.0; 3]);
+        assert!(compare_type_atom(&a, &c, &tolerance, MASK_POSITION, None).is_mismatch());
+        assert!(!compare_type_atom(&a, &c, &tolerance, MASK_POSITION, Some(&sim_box)).is_mismatch());
+    }
+
+    #[test]
+    fn test_compare_particle() {
+        let tolerance = Tolerance { position: 1e-4, velocity: 0.1, force: 1.0, other: 1e-4, mode: ErrorMode::Absolute };
+        let a: Particle = "1 Fe 0.00001 2.0 3.0 1.0 1.0 1.0 5.0 5.0 5.0 0.5 0.5 0.5".parse().unwrap();
+        let b: Particle = "1 Fe 9.99999 2.0 3.0 1.0 1.2 1.0 5.5 5.0 5.0 0.5 0.5 0.5".parse().unwrap();
+        let atom_diff = compare_particle(&a, &b, &tolerance, None);
+        let fields: Vec<&str> = atom_diff.mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, vec!["x", "vy"]);
+        assert!((atom_diff.deviations[POSITION] - 9.99998).abs() < 1e-9);
+        assert!((atom_diff.deviations[VELOCITY] - 0.2).abs() < 1e-9);
+
+        // x crosses the periodic boundary.
+        let sim_box = SimBox::orthogonal((10.0, 10.0, 10.0), [0.0; 3]).unwrap();
+        let atom_diff = compare_particle(&a, &b, &tolerance, Some(&sim_box));
+        assert_eq!(atom_diff.mismatches.len(), 1);
+        assert_eq!(atom_diff.mismatches[0].field, "vy");
+        assert!((atom_diff.deviations[FORCE] - 0.5).abs() < 1e-9);
+        assert!(atom_diff.deviations[POSITION] < 1e-4);
+
+        let c: Particle = "1 Fe 0.00001 2.0 3.0".parse().unwrap();
+        let atom_diff = compare_particle(&a, &c, &tolerance, None);
+        assert_eq!(atom_diff.mismatches.last().unwrap().field, "extra_data_len");
+        assert!(!atom_diff.deviations.contains_key(VELOCITY));
+    }
+
+    #[test]
+    fn test_relative_error() {
+        assert_eq!(relative_error(0.0, 0.0, 0.0), 0.0);
+        assert!((relative_error(1.0, -4.0, -3.0) - 0.25).abs() < 1e-9);
+
+        let tolerance = Tolerance { position: 1e-3, velocity: 1e-3, force: 1e-2, other: 1e-3, mode: ErrorMode::Relative };
+        let a = make_atom([100.0, 2.0, 3.0], [0.0, 0.0, 1e-8], [1000.0, 20.0, 30.0]);
+        let b = make_atom([100.05, 2.0, 3.0], [0.0, 0.0, 2e-8], [1001.0, 20.0, 30.0]);
+        let atom_diff = compare_type_atom(&a, &b, &tolerance, MASK_POSITION | MASK_VELOCITY | MASK_FORCE, None);
+        // small absolute deviation of vz, but large relative deviation.
+        assert_eq!(atom_diff.mismatches.len(), 1);
+        assert_eq!(atom_diff.mismatches[0].field, "vz");
+        assert!((atom_diff.mismatches[0].deviation - 0.5).abs() < 1e-9);
+    }
+}
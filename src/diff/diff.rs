@@ -1,75 +1,384 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
-use crate::xyz::xyz_reader::{Reader, Snapshot};
-use crate::xyz::particle::Particle;
+use crate::cli;
+use crate::conv::binary_parser;
+use crate::diff::atom_diff::{AtomDiff, DiffAtom, FieldMismatch, Tolerance, compare_particle, compare_type_atom};
+use crate::xyz::xyz_reader::{ReadError, Reader, Snapshot};
+use crate::xyz::particle::{parse_particles, Particle, ParticleLine};
+use crate::xyz::sim_box::SimBox;
 
-pub fn diff_wrapper(file1: &str, file2: &str, error_limit: f64, periodic_checking: bool, box_measured_size: (f64, f64, f64)) {
-    let input_1 = File::open(file1).unwrap();
-    let input_2 = File::open(file2).unwrap();
+// exit codes of `diff` sub-command, 0 means there is no difference.
+pub const EXIT_VALUE_MISMATCH: i32 = 1;
+// invalid options (e.g. malformed box), the same as the exit code of argument errors reported by clap.
+pub const EXIT_USAGE_ERROR: i32 = 2;
+pub const EXIT_SIZE_MISMATCH: i32 = 3;
+pub const EXIT_IO_ERROR: i32 = 4;
 
-    let mut reader1 = Reader::new(input_1);
-    let mut reader2 = Reader::new(input_2);
+// options of comparing two files.
+pub struct DiffConfig {
+    pub tolerance: Tolerance,
+    // compare positions under periodic boundary condition (minimum image convention).
+    pub periodic: bool,
+    // the simulation box specified by user. If it is `None`, the box in xyz file header is used.
+    pub pbc_box: Option<SimBox>,
+    // stop at the first diverging frame.
+    pub stop_at_first: bool,
+    // do not print human readable messages (e.g. the report is written to stdout).
+    pub quiet: bool,
+}
+
+// error of comparing two files, which decides the exit code.
+#[derive(Debug)]
+pub enum DiffError {
+    // the files can not be read.
+    Io(String),
+    // invalid options (e.g. no box for periodic checking).
+    Usage(String),
+}
+
+impl DiffError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            DiffError::Io(_) => EXIT_IO_ERROR,
+            DiffError::Usage(_) => EXIT_USAGE_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffError::Io(msg) | DiffError::Usage(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+// max and mean deviation of a field group (e.g. `position`) over the compared atoms.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FieldDeviation {
+    pub max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl FieldDeviation {
+    pub fn add(&mut self, deviation: f64) {
+        self.max = self.max.max(deviation);
+        self.sum += deviation;
+        self.count += 1;
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.sum / self.count as f64 }
+    }
+}
+
+// result of comparing two frames.
+pub struct FrameDiff {
+    pub frame: usize,
+    // atoms number in two frames are not the same.
+    pub size_mismatch: bool,
+    // atoms number in the frame
+    pub atoms: usize,
+    // ids of atoms whose deviation is not less than the tolerance.
+    pub mismatched_ids: Vec<u64>,
+    // every mismatched field, with atom id.
+    pub mismatches: Vec<(u64, FieldMismatch)>,
+    // number of mismatches caused by each field (e.g. `x`, `vy`).
+    pub field_counts: BTreeMap<String, usize>,
+    // atoms whose ids are not the same in two files (after sorting by id), they are not compared.
+    pub id_mismatches: usize,
+    // max and mean deviation of each field group (e.g. `position`, `velocity`) in the frame.
+    pub deviations: BTreeMap<&'static str, FieldDeviation>,
+}
+
+impl FrameDiff {
+    pub fn is_same(&self) -> bool {
+        !self.size_mismatch && self.mismatched_ids.is_empty()
+    }
+}
+
+// result of comparing two files.
+pub struct DiffReport {
+    pub frames: Vec<FrameDiff>,
+    // frames number in two files are not the same.
+    pub frames_mismatch: bool,
+}
+
+impl DiffReport {
+    // exit code of the process, based on the comparison result.
+    pub fn exit_code(&self) -> i32 {
+        if self.frames_mismatch || self.frames.iter().any(|f| f.size_mismatch) {
+            EXIT_SIZE_MISMATCH
+        } else if self.frames.iter().any(|f| !f.is_same()) {
+            EXIT_VALUE_MISMATCH
+        } else {
+            0
+        }
+    }
+
+    pub fn diverging_frames(&self) -> Vec<usize> {
+        self.frames.iter().filter(|f| !f.is_same()).map(|f| f.frame).collect()
+    }
+}
+
+/**
+ * compare two xyz files frame by frame.
+ * An error is returned if the files can not be read.
+ */
+pub fn diff_wrapper(file1: &str, file2: &str, config: &DiffConfig) -> Result<DiffReport, DiffError> {
+    let input_1 = File::open(file1).map_err(|e| DiffError::Io(format!("open file {} error: {}", file1, e)))?;
+    let input_2 = File::open(file2).map_err(|e| DiffError::Io(format!("open file {} error: {}", file2, e)))?;
 
-    let snapshot_result_1 = reader1.read_snapshot();
-    match snapshot_result_1 {
-        Err(e) => {
-            panic!("read input xyz file error: {:?}", e);
+    // columns of extxyz files are mapped by `Properties=` in comment line.
+    let to_atoms = |(frame, snapshot): (usize, Result<Snapshot<ParticleLine>, ReadError>)| match snapshot {
+        Ok(snapshot) => {
+            let atoms = parse_particles(&snapshot.comment, &snapshot.atoms)
+                .map_err(|e| format!("read input xyz file error at frame {}: {}", frame, e))?;
+            Ok((atoms, SimBox::from_extxyz_comment(&snapshot.comment)))
+        }
+        Err(e) => Err(format!("read input xyz file error at frame {}: {}", frame, e)),
+    };
+    let frames1 = Reader::new(input_1).into_frames::<ParticleLine>().enumerate().map(to_atoms);
+    let frames2 = Reader::new(input_2).into_frames::<ParticleLine>().enumerate().map(to_atoms);
+
+    let tolerance = config.tolerance;
+    diff_frames(frames1, frames2, |a: &Particle, b: &Particle, sim_box: Option<&SimBox>| {
+        compare_particle(a, b, &tolerance, sim_box)
+    }, config)
+}
+
+/**
+ * compare two binary MD files frame by frame, without converting them to xyz files.
+ * Only the fields saved in both files are compared.
+ */
+pub fn binary_diff_wrapper(file1: &str, file2: &str, bin_standard: cli::FormatStandard, ranks: u32, config: &DiffConfig)
+                           -> Result<DiffReport, DiffError> {
+    let frames1 = binary_parser::make_frame_reader(bin_standard, file1, ranks)
+        .map_err(|e| DiffError::Io(format!("read input binary file {} error: {}", file1, e)))?;
+    let frames2 = binary_parser::make_frame_reader(bin_standard, file2, ranks)
+        .map_err(|e| DiffError::Io(format!("read input binary file {} error: {}", file2, e)))?;
+
+    let mask = frames1.dump_mask() & frames2.dump_mask();
+    let tolerance = config.tolerance;
+    // box is not saved in binary files, it must be specified by user.
+    let to_frame = |atoms| Ok((atoms, None));
+    diff_frames(frames1.map(to_frame), frames2.map(to_frame), |a, b, sim_box| {
+        compare_type_atom(a, b, &tolerance, mask, sim_box)
+    }, config)
+}
+
+/**
+ * compare frames from two files frame by frame, and print the diverging frames.
+ * Each frame is atoms with the simulation box read from the file (if there is).
+ */
+fn diff_frames<A, I1, I2, C>(mut frames1: I1, mut frames2: I2, compare: C, config: &DiffConfig) -> Result<DiffReport, DiffError>
+    where A: DiffAtom, I1: Iterator<Item=Result<(Vec<A>, Option<SimBox>), String>>,
+          I2: Iterator<Item=Result<(Vec<A>, Option<SimBox>), String>>,
+          C: Fn(&A, &A, Option<&SimBox>) -> AtomDiff {
+    let mut report = DiffReport { frames: Vec::new(), frames_mismatch: false };
+    loop {
+        let frame = report.frames.len();
+        let ((mut atoms1, box1), (mut atoms2, _)) = match (frames1.next(), frames2.next()) {
+            (None, None) => break,
+            // read error of either file takes precedence over the mismatched frames number.
+            (Some(Err(e)), _) | (_, Some(Err(e))) => return Err(DiffError::Io(e)),
+            (Some(_), None) | (None, Some(_)) => {
+                if !config.quiet {
+                    println!("mismatched frames number in two files: only {} frame(s) in one of the files", frame);
+                }
+                report.frames_mismatch = true;
+                break;
+            }
+            (Some(Ok(a)), Some(Ok(b))) => (a, b),
+        };
+
+        // the box specified by user takes precedence over the box in file.
+        let sim_box = if config.periodic {
+            match config.pbc_box.or(box1) {
+                Some(sim_box) => Some(sim_box),
+                None => return Err(DiffError::Usage(format!("no simulation box for periodic checking at frame {}, please specify it by `--box`", frame))),
+            }
+        } else {
+            None
+        };
+        let frame_diff = run_diff(frame, &mut atoms1, &mut atoms2, |a, b| compare(a, b, sim_box.as_ref()), config.quiet);
+        if !config.quiet {
+            print_frame_summary(&frame_diff);
+        }
+        let same = frame_diff.is_same();
+        report.frames.push(frame_diff);
+        if !same && config.stop_at_first {
+            if !config.quiet {
+                println!("stop at the first diverging frame {}.", frame);
+            }
+            break;
         }
-        Ok(ref _snapshot) => {}
     }
 
-    let snapshot_result_2 = reader2.read_snapshot();
-    match snapshot_result_2 {
-        Err(e) => {
-            panic!("read input xyz file error: {:?}", e);
+    if !config.quiet {
+        let diverging_frames = report.diverging_frames();
+        if diverging_frames.is_empty() && !report.frames_mismatch {
+            println!("no difference.")
+        } else {
+            println!("diverging frames: {:?}", diverging_frames);
         }
-        Ok(ref _snapshot) => {}
     }
+    Ok(report)
+}
 
-    diff(&mut snapshot_result_1.unwrap(), &mut snapshot_result_2.unwrap(), error_limit, periodic_checking, box_measured_size);
+fn print_frame_summary(frame_diff: &FrameDiff) {
+    let frame = frame_diff.frame;
+    if frame_diff.size_mismatch {
+        println!("frame {}: mismatched atom size in two files", frame);
+        return;
+    }
+    println!("frame {}: {} mismatched atom(s), {} mismatched id(s)", frame, frame_diff.mismatched_ids.len(), frame_diff.id_mismatches);
+    for (group, deviation) in &frame_diff.deviations {
+        println!("frame {}: {} max deviation: {:e}, mean deviation: {:e}", frame, group, deviation.max, deviation.mean());
+    }
+    if !frame_diff.mismatched_ids.is_empty() {
+        println!("frame {}: mismatched atom ids: {:?}", frame, frame_diff.mismatched_ids);
+        println!("frame {}: mismatched fields: {:?}", frame, frame_diff.field_counts);
+    }
 }
 
-/** run_diff returns true if there is no difference between two snapshots.
- * for closure `diff`, it return true if there is no difference between two atoms.
- *
+/** run_diff compares atoms in two frames one by one (sorted by id).
+ * for closure `compare`, it returns the comparison result of two atoms with the same id.
  */
-pub fn run_diff<P>(snapshot1: &mut Snapshot<Particle>, snapshot2: &mut Snapshot<Particle>, atom_diff: P) -> bool
-    where P: (Fn(&Particle, &Particle) -> bool) {
-    if snapshot1.size() != snapshot2.size() {
-        println!("mismatched atom size in two files");
-        return false;
-    }
-    let cmp = |a: &Particle, b: &Particle| {
-        a.id.cmp(&b.id)
+pub fn run_diff<A, C>(frame: usize, atoms1: &mut [A], atoms2: &mut [A], compare: C, quiet: bool) -> FrameDiff
+    where A: DiffAtom, C: Fn(&A, &A) -> AtomDiff {
+    let mut frame_diff = FrameDiff {
+        frame,
+        size_mismatch: false,
+        atoms: atoms1.len(),
+        mismatched_ids: Vec::new(),
+        mismatches: Vec::new(),
+        field_counts: BTreeMap::new(),
+        id_mismatches: 0,
+        deviations: BTreeMap::new(),
     };
-    snapshot1.atoms.sort_by(cmp);
-    snapshot2.atoms.sort_by(cmp);
-
-    let num_atoms = snapshot1.size();
-    let mut flag = true;
-    for i in 0..num_atoms {
-        if !atom_diff(&snapshot1.atoms[i], &snapshot2.atoms[i]) {
-            println!("mismatch atom position or velocity: \n{}\n{}",
-                     snapshot1.atoms[i].to_string(), snapshot2.atoms[i].to_string());
-            flag = false;
+    if atoms1.len() != atoms2.len() {
+        frame_diff.size_mismatch = true;
+        return frame_diff;
+    }
+    atoms1.sort_by_key(|a| a.id());
+    atoms2.sort_by_key(|a| a.id());
+
+    for (a, b) in atoms1.iter().zip(atoms2.iter()) {
+        if a.id() != b.id() {
+            if !quiet {
+                println!("mismatch atom id: \n{}\n{}", a.describe(), b.describe());
+            }
+            frame_diff.mismatched_ids.push(a.id());
+            frame_diff.mismatches.push((a.id(), FieldMismatch {
+                field: String::from("id"),
+                value1: a.id() as f64,
+                value2: b.id() as f64,
+                deviation: (a.id() as f64 - b.id() as f64).abs(),
+            }));
+            *frame_diff.field_counts.entry(String::from("id")).or_insert(0) += 1;
+            frame_diff.id_mismatches += 1;
+            continue;
+        }
+        let atom_diff = compare(a, b);
+        for (group, deviation) in &atom_diff.deviations {
+            frame_diff.deviations.entry(*group).or_default().add(*deviation);
+        }
+        if atom_diff.is_mismatch() {
+            if !quiet {
+                let fields: Vec<String> = atom_diff.mismatches.iter().map(|m| m.to_string()).collect();
+                println!("mismatch atom: \n{}\n{}\n{}", a.describe(), b.describe(), fields.join("\n"));
+            }
+            frame_diff.mismatched_ids.push(a.id());
+            for mismatch in atom_diff.mismatches {
+                *frame_diff.field_counts.entry(mismatch.field.clone()).or_insert(0) += 1;
+                frame_diff.mismatches.push((a.id(), mismatch));
+            }
         }
     }
-    return flag;
+    frame_diff
 }
 
-fn diff(snapshot1: &mut Snapshot<Particle>, snapshot2: &mut Snapshot<Particle>, error_limit: f64, periodic_checking: bool, box_measured_size: (f64, f64, f64)) {
-    let same_flag;
-    if periodic_checking {
-        let comp_with_periodic_checking = |a: &Particle, b: &Particle| -> bool {
-            return a.is_status_near_eq_with_pbc(b, error_limit, box_measured_size);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::ErrorMode;
+    use crate::conv::binary_types::TypeAtom;
+    use crate::conv::v2_atom_types::{MASK_POSITION, MASK_VELOCITY};
+    use crate::conv::writers::extxyz_out_writer;
+    use crate::conv::writers::out_writer::WriteProgress;
+
+    // write a frame to extxyz file by the extxyz writer of `conv`.
+    fn write_extxyz(path: &str, atoms: &[TypeAtom]) {
+        let mut writer = extxyz_out_writer::new_writer(path, 6);
+        writer.on_start(path, MASK_POSITION | MASK_VELOCITY);
+        writer.before_frame(0, path);
+        for atom in atoms {
+            writer.on_atom_read(atom);
+        }
+        writer.after_frame();
+        writer.done();
+    }
+
+    #[test]
+    fn test_diff_extxyz() {
+        let atom = |id: u64, tp: i32, x: f64, vx: f64| TypeAtom {
+            id,
+            tp,
+            inter_type: 0,
+            atom_location: [x, 1.0, 2.0],
+            atom_velocity: [vx, 0.0, 0.0],
+            atom_force: [0.0; 3],
         };
-        same_flag = run_diff(snapshot1, snapshot2, comp_with_periodic_checking);
-    } else {
-        let comp = |a: &Particle, b: &Particle| -> bool {
-            return a.is_status_near_eq(b, error_limit);
+        let dir = std::env::temp_dir();
+        let file1 = dir.join(format!("md-tools-diff-{}-1.xyz", std::process::id())).to_string_lossy().to_string();
+        let file2 = dir.join(format!("md-tools-diff-{}-2.xyz", std::process::id())).to_string_lossy().to_string();
+        write_extxyz(&file1, &[atom(1, 0, 0.5, 0.1), atom(2, 1, 1.5, 0.2)]);
+        write_extxyz(&file2, &[atom(1, 0, 0.5, 0.1), atom(2, 1, 1.5, 0.3)]);
+
+        let config = DiffConfig {
+            tolerance: Tolerance { position: 1e-4, velocity: 1e-4, force: 1e-4, other: 1e-4, mode: ErrorMode::Absolute },
+            periodic: false,
+            pbc_box: None,
+            stop_at_first: false,
+            quiet: true,
         };
-        same_flag = run_diff(snapshot1, snapshot2, comp);
+        let same = diff_wrapper(&file1, &file1, &config);
+        let different = diff_wrapper(&file1, &file2, &config);
+        std::fs::remove_file(&file1).unwrap();
+        std::fs::remove_file(&file2).unwrap();
+
+        assert_eq!(same.unwrap().exit_code(), 0);
+        let report = different.unwrap();
+        assert_eq!(report.exit_code(), EXIT_VALUE_MISMATCH);
+        assert_eq!(report.frames[0].mismatched_ids, vec![2]);
+        assert_eq!(report.frames[0].mismatches[0].1.field, "vx");
     }
-    if same_flag {
-        println!("no difference.")
+
+    #[test]
+    fn test_diff_frames_error() {
+        let config = DiffConfig {
+            tolerance: Tolerance { position: 1e-4, velocity: 1e-4, force: 1e-4, other: 1e-4, mode: ErrorMode::Absolute },
+            periodic: true,
+            pbc_box: None,
+            stop_at_first: false,
+            quiet: true,
+        };
+        let compare = |a: &TypeAtom, b: &TypeAtom, sim_box: Option<&SimBox>| {
+            compare_type_atom(a, b, &config.tolerance, MASK_POSITION, sim_box)
+        };
+        let atom = TypeAtom { id: 1, tp: 0, inter_type: 0, atom_location: [0.0; 3], atom_velocity: [0.0; 3], atom_force: [0.0; 3] };
+        let frame = || Ok((vec![atom], None));
+
+        // read error of one file is not reported as mismatched frames number.
+        let bad = vec![Err(String::from("bad frame"))];
+        let result = diff_frames(bad.into_iter(), Vec::new().into_iter(), compare, &config);
+        assert_eq!(result.err().unwrap().exit_code(), EXIT_IO_ERROR);
+
+        // no box for periodic checking.
+        let result = diff_frames(vec![frame()].into_iter(), vec![frame()].into_iter(), compare, &config);
+        assert_eq!(result.err().unwrap().exit_code(), EXIT_USAGE_ERROR);
     }
 }
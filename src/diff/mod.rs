@@ -1 +1,3 @@
 pub(crate) mod diff;
+pub(crate) mod atom_diff;
+pub(crate) mod report;
@@ -0,0 +1,146 @@
+// Machine-readable report (json or csv) of `diff` sub-command.
+
+use std::io::{self, Write};
+use crate::cli::ReportFormat;
+use std::collections::BTreeMap;
+use crate::diff::diff::{DiffReport, FieldDeviation, EXIT_SIZE_MISMATCH, EXIT_VALUE_MISMATCH};
+use crate::json;
+
+const CSV_HEADER: &str = "frame,id,field,value1,value2,deviation\n";
+
+// status of comparison in report, based on the exit code.
+fn status(report: &DiffReport) -> &'static str {
+    match report.exit_code() {
+        0 => "same",
+        EXIT_VALUE_MISMATCH => "value_mismatch",
+        EXIT_SIZE_MISMATCH => "size_mismatch",
+        _ => "unknown",
+    }
+}
+
+// deviations of field groups as a json object, e.g. `{"position": 0.5, "velocity": 0.1}`.
+fn json_deviations(deviations: &BTreeMap<&'static str, FieldDeviation>, value: impl Fn(&FieldDeviation) -> f64) -> String {
+    json::object(deviations.iter().map(|(group, deviation)| (group, json::number(value(deviation)))))
+}
+
+pub fn write_report<W: Write>(writer: &mut W, format: ReportFormat, report: &DiffReport) -> io::Result<()> {
+    match format {
+        ReportFormat::Json => write_json(writer, report),
+        ReportFormat::Csv => write_csv(writer, report),
+    }
+}
+
+// the summary block of report, as a list of key and value (value is already formatted as json).
+fn summary(report: &DiffReport) -> Vec<(&'static str, String)> {
+    let mismatched_atoms: usize = report.frames.iter().map(|f| f.mismatched_ids.len()).sum();
+    let mismatches: usize = report.frames.iter().map(|f| f.mismatches.len()).sum();
+    let id_mismatches: usize = report.frames.iter().map(|f| f.id_mismatches).sum();
+    // max deviation of each field group in all frames.
+    let mut max_deviations: BTreeMap<&'static str, FieldDeviation> = BTreeMap::new();
+    for f in &report.frames {
+        for (group, deviation) in &f.deviations {
+            let max = max_deviations.entry(*group).or_default();
+            max.max = max.max.max(deviation.max);
+        }
+    }
+    let diverging: Vec<String> = report.diverging_frames().iter().map(|f| f.to_string()).collect();
+    vec![
+        ("status", json::string(status(report))),
+        ("exit_code", report.exit_code().to_string()),
+        ("frames", report.frames.len().to_string()),
+        ("frames_mismatch", report.frames_mismatch.to_string()),
+        ("diverging_frames", json::array(&diverging)),
+        ("mismatched_atoms", mismatched_atoms.to_string()),
+        ("mismatches", mismatches.to_string()),
+        ("id_mismatches", id_mismatches.to_string()),
+        ("max_deviation", json_deviations(&max_deviations, |d| d.max)),
+    ]
+}
+
+fn write_json<W: Write>(writer: &mut W, report: &DiffReport) -> io::Result<()> {
+    let frames: Vec<String> = report.frames.iter().map(|f| {
+        format!("{{\"frame\": {}, \"atoms\": {}, \"size_mismatch\": {}, \"mismatched_atoms\": {}, \"id_mismatches\": {}, \
+        \"max_deviation\": {}, \"mean_deviation\": {}}}",
+                f.frame, f.atoms, f.size_mismatch, f.mismatched_ids.len(), f.id_mismatches,
+                json_deviations(&f.deviations, |d| d.max), json_deviations(&f.deviations, |d| d.mean()))
+    }).collect();
+
+    let mut mismatches: Vec<String> = Vec::new();
+    for f in &report.frames {
+        for (id, m) in &f.mismatches {
+            mismatches.push(format!("{{\"frame\": {}, \"id\": {}, \"field\": {}, \"value1\": {}, \"value2\": {}, \"deviation\": {}}}",
+                                    f.frame, id, json::string(&m.field),
+                                    json::number(m.value1), json::number(m.value2), json::number(m.deviation)));
+        }
+    }
+    let items = vec![
+        ("summary", json::pretty_object(summary(report), 2)),
+        ("frames", json::pretty_array(&frames, 2)),
+        ("mismatches", json::pretty_array(&mismatches, 2)),
+    ];
+    writeln!(writer, "{}", json::pretty_object(items, 0))
+}
+
+// csv report: the summary block is written as comment lines (start with `#`), followed by mismatches table.
+fn write_csv<W: Write>(writer: &mut W, report: &DiffReport) -> io::Result<()> {
+    for (key, value) in summary(report) {
+        writeln!(writer, "# {}: {}", key, value)?;
+    }
+    writer.write_all(CSV_HEADER.as_bytes())?;
+    for f in &report.frames {
+        for (id, m) in &f.mismatches {
+            writeln!(writer, "{},{},{},{},{},{}", f.frame, id, m.field, m.value1, m.value2, m.deviation)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::atom_diff::FieldMismatch;
+    use crate::diff::diff::FrameDiff;
+
+    fn make_report() -> DiffReport {
+        let mismatch = FieldMismatch { field: String::from("vx"), value1: 1.0, value2: 1.5, deviation: 0.5 };
+        let mut field_counts = BTreeMap::new();
+        field_counts.insert(String::from("vx"), 1);
+        let mut velocity = FieldDeviation::default();
+        velocity.add(0.5);
+        velocity.add(0.0);
+        let mut deviations = BTreeMap::new();
+        deviations.insert("velocity", velocity);
+        DiffReport {
+            frames: vec![FrameDiff {
+                frame: 0,
+                size_mismatch: false,
+                atoms: 2,
+                mismatched_ids: vec![7],
+                mismatches: vec![(7, mismatch)],
+                field_counts,
+                id_mismatches: 0,
+                deviations,
+            }],
+            frames_mismatch: false,
+        }
+    }
+
+    #[test]
+    fn test_csv_report() {
+        let mut out: Vec<u8> = Vec::new();
+        write_report(&mut out, ReportFormat::Csv, &make_report()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("# status: \"value_mismatch\"\n# exit_code: 1\n"));
+        assert!(out.ends_with("frame,id,field,value1,value2,deviation\n0,7,vx,1,1.5,0.5\n"));
+    }
+
+    #[test]
+    fn test_json_report() {
+        let mut out: Vec<u8> = Vec::new();
+        write_report(&mut out, ReportFormat::Json, &make_report()).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\"diverging_frames\": [0]"));
+        assert!(out.contains("\"max_deviation\": {\"velocity\": 0.5}, \"mean_deviation\": {\"velocity\": 0.25}"));
+        assert!(out.contains("{\"frame\": 0, \"id\": 7, \"field\": \"vx\", \"value1\": 1, \"value2\": 1.5, \"deviation\": 0.5}"));
+    }
+}
@@ -0,0 +1,87 @@
+// Helpers for writing json output (reports and summaries) by hand.
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+// path of the json summary of an output table (e.g. `defects.csv`): the extension is replaced by `.summary.json`,
+// thus, the summary is found next to the table and is not overwritten by tables of other input files.
+pub fn summary_path(output: &Path) -> PathBuf {
+    output.with_extension("summary.json")
+}
+
+// format float number. Infinity and NaN are not valid json numbers, they are written as null.
+pub fn number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        String::from("null")
+    }
+}
+
+pub fn string(value: &str) -> String {
+    let mut s = String::with_capacity(value.len() + 2);
+    s.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if (c as u32) < 0x20 => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+// value (already formatted as json) or null.
+pub fn optional(value: Option<String>) -> String {
+    value.unwrap_or_else(|| String::from("null"))
+}
+
+// object in a single line, e.g. `{"bcc": 2, "fcc": 1}`. The values are already formatted as json.
+pub fn object<K: Display>(items: impl IntoIterator<Item=(K, String)>) -> String {
+    let items: Vec<String> = items.into_iter().map(|(key, value)| format!("{}: {}", string(&key.to_string()), value)).collect();
+    format!("{{{}}}", items.join(", "))
+}
+
+// array in a single line, e.g. `[1, 2, 3]`.
+pub fn array(items: &[String]) -> String {
+    format!("[{}]", items.join(", "))
+}
+
+// object with an item per line, nested in `indent` spaces.
+pub fn pretty_object<K: Display>(items: impl IntoIterator<Item=(K, String)>, indent: usize) -> String {
+    let items: Vec<String> = items.into_iter()
+        .map(|(key, value)| format!("{:indent$}{}: {}", "", string(&key.to_string()), value, indent = indent + 2))
+        .collect();
+    format!("{{\n{}\n{:indent$}}}", items.join(",\n"), "", indent = indent)
+}
+
+// array with an item per line, nested in `indent` spaces.
+pub fn pretty_array(items: &[String], indent: usize) -> String {
+    if items.is_empty() {
+        return String::from("[]");
+    }
+    let items: Vec<String> = items.iter().map(|item| format!("{:indent$}{}", "", item, indent = indent + 2)).collect();
+    format!("[\n{}\n{:indent$}]", items.join(",\n"), "", indent = indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        assert_eq!(number(0.5), "0.5");
+        assert_eq!(number(f64::INFINITY), "null");
+        assert_eq!(string("a \"b\"\\\n"), "\"a \\\"b\\\"\\\\\\n\"");
+        assert_eq!(object(vec![("a\"", String::from("1")), ("b", string("c"))]), "{\"a\\\"\": 1, \"b\": \"c\"}");
+        assert_eq!(pretty_object(vec![("a", String::from("1")), ("b", pretty_array(&[String::from("2")], 2))], 0),
+                   "{\n  \"a\": 1,\n  \"b\": [\n    2\n  ]\n}");
+        assert_eq!(pretty_array(&[], 2), "[]");
+        assert_eq!(summary_path(Path::new("out/defects.csv")), Path::new("out/defects.summary.json"));
+    }
+}
@@ -1,16 +1,22 @@
 use clap::Parser;
 
+use std::fs::File;
 use std::path::{Path, PathBuf};
 use crate::cli::AnsAlgorithm;
+use crate::xyz::sim_box::SimBox;
 
-use crate::conv::{binary_parser};
-use crate::conv::writers::{bin_out_writer, dump_out_writer, text_out_writer, xyz_out_writer};
+use crate::conv::{binary_parser, file_info};
+use crate::conv::frame_range::FrameRange;
+use crate::conv::writers::{bin_out_writer, cna_out_writer, columns, dump_out_writer, extxyz_out_writer, text_out_writer, xyz_out_writer};
+use crate::conv::writers::out_writer::WriteProgress;
 
 mod ans;
 mod diff;
 mod xyz;
 mod conv;
 mod cli;
+mod traj;
+mod json;
 
 fn main() {
     let args = cli::Cli::parse();
@@ -18,35 +24,196 @@ fn main() {
     match &args.command {
         cli::Commands::Conv {
             dry, input, output, format,
-            precision, standard, ranks
+            precision, standard, ranks, cna, sim_box, box_origin, columns, frames
         } => {
-            parse_convert(dry.clone(), standard.clone(), &input, output.clone(), format.clone(), ranks.clone(), precision.clone());
+            let sim_box = match make_sim_box(sim_box, box_origin) {
+                Ok(sim_box) => sim_box,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            let columns = match columns.as_ref().map(|spec| columns::parse_columns(spec)).transpose() {
+                Ok(columns) => columns,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            let frame_range = match frames.as_ref().map(|spec| FrameRange::parse(spec)).transpose() {
+                Ok(frame_range) => frame_range.unwrap_or_else(FrameRange::all),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            };
+            let options = ConvertOptions { format: *format, precision: *precision, cna: *cna, sim_box, columns, frame_range };
+            parse_convert(*dry, *standard, input, output.clone(), *ranks, &options);
             return;
         }
-        cli::Commands::Diff { error, file_1, file_2, periodic_checking, sim_box } => {
-            parse_diff(error.clone(), file_1.clone(), file_2.clone(), periodic_checking.clone(), sim_box);
-            return;
+        cli::Commands::Diff {
+            error, pos_error, velo_error, force_error, error_mode, binary, standard, ranks,
+            file_1, file_2, periodic_checking, sim_box, box_origin, stop_at_first, report, report_output
+        } => {
+            let sim_box = match make_sim_box(sim_box, box_origin) {
+                Ok(sim_box) => sim_box,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(diff::diff::EXIT_USAGE_ERROR);
+                }
+            };
+            let tolerance = diff::atom_diff::Tolerance {
+                position: pos_error.unwrap_or(*error),
+                velocity: velo_error.unwrap_or(*error),
+                force: force_error.unwrap_or(*error),
+                other: *error,
+                mode: *error_mode,
+            };
+            let binary = if *binary {
+                match (standard, ranks) {
+                    (cli::FormatStandard::Current, None) | (cli::FormatStandard::Current, Some(0)) => {
+                        eprintln!("ranks must be specified for binary files of `current` standard.");
+                        std::process::exit(diff::diff::EXIT_USAGE_ERROR);
+                    }
+                    (_, ranks) => Some((*standard, ranks.unwrap_or(0) as u32)),
+                }
+            } else {
+                None
+            };
+            if *periodic_checking && binary.is_some() && sim_box.is_none() {
+                eprintln!("the simulation box (`--box`) must be specified for periodic checking of binary files.");
+                std::process::exit(diff::diff::EXIT_USAGE_ERROR);
+            }
+            let config = diff::diff::DiffConfig {
+                tolerance,
+                periodic: *periodic_checking,
+                pbc_box: sim_box,
+                stop_at_first: *stop_at_first,
+                // report is written to stdout
+                quiet: report.is_some() && report_output.is_none(),
+            };
+            std::process::exit(parse_diff(file_1, file_2, binary, &config, *report, report_output.as_deref()));
         }
         cli::Commands::Ans {
             input, output, verbose, input_from_minio, box_start,
-            box_size, algorithm
+            box_size, lattice_const, auto_lattice_const, boundary, cluster, time_series,
+            binary, standard, ranks, algorithm, csp_neighbors
         } => {
-            parse_ans(input, output, verbose.clone(), input_from_minio.clone(), box_start.clone(), box_size.clone(), algorithm.clone());
+            let binary = if *binary {
+                match (standard, ranks) {
+                    (cli::FormatStandard::Current, None) | (cli::FormatStandard::Current, Some(0)) => {
+                        eprintln!("ranks must be specified for binary files of `current` standard.");
+                        return;
+                    }
+                    (_, ranks) => Some((*standard, ranks.unwrap_or(0) as u32)),
+                }
+            } else {
+                None
+            };
+            let options = AnsOptions {
+                verbose: *verbose,
+                input_from_minio: *input_from_minio,
+                box_start: box_start.clone(),
+                box_size: box_size.clone(),
+                lattice_const: *lattice_const,
+                auto_lattice_const: *auto_lattice_const,
+                boundary: boundary.clone(),
+                cluster_shell: *cluster,
+                time_series: *time_series,
+                binary,
+                algorithm: *algorithm,
+                csp_neighbors: *csp_neighbors,
+            };
+            parse_ans(input, output, options);
             return;
         }
+        cli::Commands::Rdf {
+            input, output, format, standard, ranks, cutoff, bins, sim_box, box_origin, verbose
+        } => {
+            let result = input_config(*format, *standard, *ranks).and_then(|config| {
+                let sim_box = make_sim_box(sim_box, box_origin)?;
+                parse_rdf(input, output, config, *cutoff, *bins, sim_box, *verbose)
+            });
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        cli::Commands::Msd {
+            input, output, format, standard, ranks, dt, sim_box, box_origin, verbose
+        } => {
+            let result = input_config(*format, *standard, *ranks).and_then(|config| {
+                let sim_box = make_sim_box(sim_box, box_origin)?;
+                traj::msd::msd_wrapper(&input.to_string_lossy(), output, config, *dt, sim_box, *verbose)
+            });
+            match result {
+                Ok(_) => println!("MSD saved at {}", output),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        cli::Commands::Displacement {
+            input, output, format, standard, ranks, reference, threshold, sim_box, box_origin, precision
+        } => {
+            let result = input_config(*format, *standard, *ranks).and_then(|config| {
+                let sim_box = make_sim_box(sim_box, box_origin)?;
+                let writer = dump_out_writer::new_truncated_writer(output, *precision);
+                traj::displacement::displacement_wrapper(&input.to_string_lossy(), output, config, *reference, *threshold,
+                                                         sim_box, writer)
+            });
+            match result {
+                Ok(_) => println!("displacements saved at {}", output),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        cli::Commands::Info { input, standard, ranks, json, output } => {
+            std::process::exit(parse_info(&input.to_string_lossy(), *standard, *ranks, *json, output.as_deref()));
+        }
     }
 }
 
+// options of `conv` sub-command, shared by all input files.
+struct ConvertOptions {
+    format: cli::OutFormat,
+    precision: u32,
+    // identify structure type of atoms and write it as an extra column.
+    cna: bool,
+    // the simulation box specified by user, passed to the writer.
+    sim_box: Option<SimBox>,
+    columns: Option<Vec<columns::Column>>,
+    frame_range: FrameRange,
+}
+
 fn parse_convert(dry_run: bool, bin_standard: cli::FormatStandard, input_files: &Vec<PathBuf>, output: String,
-                 format: cli::OutFormat, ranks: usize, precision: u32) {
-    if ranks <= (0 as usize) {
-        println!("unsupported ranks value.");
+                 ranks: Option<usize>, options: &ConvertOptions) {
+    let format = options.format;
+    let ranks = match (bin_standard, ranks) {
+        (cli::FormatStandard::Current, None) | (cli::FormatStandard::Current, Some(0)) => {
+            println!("ranks must be specified for binary files of `current` standard.");
+            return;
+        }
+        (_, ranks) => ranks.unwrap_or(0),
+    };
+    if !(format == cli::OutFormat::Xyz || format == cli::OutFormat::Extxyz || format == cli::OutFormat::Dump || format == cli::OutFormat::Bin || format == cli::OutFormat::Text) {
+        println!("unsupported format.");
         return;
     }
-    if !(format == cli::OutFormat::Xyz || format == cli::OutFormat::Dump || format == cli::OutFormat::Bin || format == cli::OutFormat::Text) {
-        println!("unsupported format.");
+    if options.cna && format == cli::OutFormat::Bin {
+        println!("structure identification (`--cna`) is not supported by `bin` format.");
+        return;
+    }
+    if options.columns.is_some() && format != cli::OutFormat::Dump && format != cli::OutFormat::Text {
+        println!("column selection (`--columns`) is only supported by `dump` and `text` formats.");
         return;
     }
+    if options.sim_box.is_some() && format != cli::OutFormat::Dump && format != cli::OutFormat::Extxyz && !options.cna {
+        println!("Warning: the simulation box (`--box`) is only used by `dump` and `extxyz` formats and `--cna`.");
+    }
 
     if input_files.len() == 0 {
         println!("no matching input files");
@@ -70,14 +237,38 @@ fn parse_convert(dry_run: bool, bin_standard: cli::FormatStandard, input_files:
         };
 
         if !dry_run {
-            mk_parse(format, precision, bin_standard, ranks as u32, input_file.to_str().unwrap(), output_file_path.as_str());
+            let result = mk_parse(bin_standard, ranks as u32, input_file.to_str().unwrap(), output_file_path.as_str(), options);
+            if let Err(e) = result {
+                println!("convert file {} failed: {}", input_file.to_str().unwrap(), e);
+                return;
+            }
         }
         println!("file {} converted, saved at {}", input_file.to_str().unwrap(), output_file_path.as_str());
     }
 }
 
-fn parse_ans(input: &Vec<PathBuf>, output: &Vec<String>, verbose_log: bool, input_from_minio: bool,
-             _box_start: Vec<f64>, box_size: Vec<u64>, _algorithm: AnsAlgorithm) {
+// options of `ans` sub-command.
+struct AnsOptions {
+    verbose: bool,
+    input_from_minio: bool,
+    box_start: Vec<f64>,
+    box_size: Vec<u64>,
+    lattice_const: Option<f64>,
+    auto_lattice_const: bool,
+    boundary: Vec<cli::Boundary>,
+    cluster_shell: Option<usize>,
+    time_series: bool,
+    // standard and ranks of binary input files, or `None` for xyz files.
+    binary: Option<(cli::FormatStandard, u32)>,
+    algorithm: AnsAlgorithm,
+    csp_neighbors: usize,
+}
+
+fn parse_ans(input: &Vec<PathBuf>, output: &Vec<String>, options: AnsOptions) {
+    let AnsOptions {
+        verbose: verbose_log, input_from_minio, box_start: _box_start, box_size, lattice_const, auto_lattice_const,
+        boundary, cluster_shell, time_series, binary, algorithm, csp_neighbors,
+    } = options;
     if box_size.len() != 0 && box_size.len() != 3 {
         panic!("length of box size must be 3.");
     }
@@ -93,10 +284,56 @@ fn parse_ans(input: &Vec<PathBuf>, output: &Vec<String>, verbose_log: bool, inpu
     }
 
     let mut box_config = ans::box_config::BoxConfig {
+        lattice: match algorithm {
+            // lattice type is not used by structure identification.
+            AnsAlgorithm::WS | AnsAlgorithm::Cna | AnsAlgorithm::Csp => ans::lattice::LatticeType::Bcc,
+            AnsAlgorithm::FccWS => ans::lattice::LatticeType::Fcc,
+        },
         input_box_start: box_start,
         input_box_size: box_size,
+        input_lattice_const: lattice_const.map(|a| a as ans::voronoy::Float),
+        auto_lattice_const,
+        lattice_const: 0.0,
         box_size_: (0, 0, 0),
         box_start: (0.0, 0.0, 0.0),
+        periodic: [true; 3],
+        cluster_shell,
+    };
+    for (d, b) in boundary.iter().enumerate() {
+        box_config.periodic[d] = *b == cli::Boundary::P;
+    }
+
+    let per_atom = algorithm == AnsAlgorithm::Cna || algorithm == AnsAlgorithm::Csp;
+    if per_atom && time_series {
+        println!("time series mode (`--time-series`) is not supported by `cna` and `csp` algorithms");
+        return;
+    }
+    if algorithm == AnsAlgorithm::Csp && (csp_neighbors == 0 || csp_neighbors % 2 != 0) {
+        println!("the number of neighbors for centrosymmetry parameter must be a positive even number");
+        return;
+    }
+    let mut analyse_file = |input_file: &str, output_file: &str| {
+        if per_atom {
+            let periodic = box_config.periodic;
+            let result = if algorithm == AnsAlgorithm::Cna {
+                ans::per_atom::per_atom_wrapper(&ans::cna::Cna, input_file, output_file, binary, periodic, verbose_log)
+            } else {
+                let csp = ans::csp::Csp { neighbors: csp_neighbors };
+                ans::per_atom::per_atom_wrapper(&csp, input_file, output_file, binary, periodic, verbose_log)
+            };
+            if let Err(e) = result {
+                println!("{}", e);
+            }
+        } else if time_series {
+            // all frames in the file are analysed.
+            if let Err(e) = ans::time_series::time_series_wrapper(input_file, output_file, binary, &mut box_config, verbose_log) {
+                println!("{}", e);
+            }
+        } else if let Some((standard, ranks)) = binary {
+            ans::analysis::binary_analysis_wrapper(input_file, output_file, standard, ranks, &mut box_config, verbose_log);
+        } else {
+            ans::analysis::analysis_wrapper(input_file, output_file, input_from_minio, &mut box_config, verbose_log);
+        }
     };
 
     if input_files.len() == 0 {
@@ -106,8 +343,7 @@ fn parse_ans(input: &Vec<PathBuf>, output: &Vec<String>, verbose_log: bool, inpu
     if output_files.len() == input_files.len() {
         for i in 0..output_files.len() {
             println!("analysing file {}", input_files[i].to_str().unwrap());
-            ans::analysis::analysis_wrapper(input_files[i].to_str().unwrap(), output_files[i].as_str(),
-                                            input_from_minio, &mut box_config, verbose_log);
+            analyse_file(input_files[i].to_str().unwrap(), output_files[i].as_str());
             println!("file {} analysis, saved at {}", input_files[i].to_str().unwrap(), output_files[i]);
         }
     } else {
@@ -120,8 +356,7 @@ fn parse_ans(input: &Vec<PathBuf>, output: &Vec<String>, verbose_log: bool, inpu
                 println!("analysing file {}", input_file);
                 let output_prefix = input_path.file_name().unwrap().to_str().unwrap();
                 let output_file_path = format!("{}-{}", output_prefix, output);
-                ans::analysis::analysis_wrapper(input_file, output_file_path.as_str(),
-                                                input_from_minio, &mut box_config, verbose_log);
+                analyse_file(input_file, output_file_path.as_str());
                 println!("file {} analysis, saved at {}", input_file, output_file_path.as_str());
             }
         } else {
@@ -131,35 +366,142 @@ fn parse_ans(input: &Vec<PathBuf>, output: &Vec<String>, verbose_log: bool, inpu
     }
 
     // todo method
-    // todo, now only xyz format input is supported
 }
 
-fn mk_parse(format: cli::OutFormat, precision: u32, bin_standard: cli::FormatStandard, ranks: u32, input: &str, output: &str) {
-    match format {
+fn mk_parse(bin_standard: cli::FormatStandard, ranks: u32, input: &str, output: &str, options: &ConvertOptions)
+            -> Result<(), binary_parser::ParseError> {
+    let precision = options.precision;
+    match options.format {
         cli::OutFormat::Xyz => {
-            binary_parser::parse_wrapper(bin_standard, input, output, ranks, xyz_out_writer::new_writer(output, precision)).unwrap();
+            parse_with_writer(bin_standard, input, output, ranks, xyz_out_writer::new_writer(output, precision), options)
+        }
+        cli::OutFormat::Extxyz => {
+            parse_with_writer(bin_standard, input, output, ranks, extxyz_out_writer::new_writer(output, precision), options)
         }
         cli::OutFormat::Text => {
-            binary_parser::parse_wrapper(bin_standard, input, output, ranks, text_out_writer::new_writer(output, precision)).unwrap();
+            let mut writer = text_out_writer::new_writer(output, precision);
+            if let Some(columns) = &options.columns {
+                writer.set_columns(columns.clone());
+            }
+            parse_with_writer(bin_standard, input, output, ranks, writer, options)
         }
         cli::OutFormat::Dump => {
-            binary_parser::parse_wrapper(bin_standard, input, output, ranks, dump_out_writer::new_writer(output, precision)).unwrap();
+            let mut writer = dump_out_writer::new_writer(output, precision);
+            if let Some(columns) = &options.columns {
+                writer.set_columns(columns.clone());
+            }
+            parse_with_writer(bin_standard, input, output, ranks, writer, options)
         }
         cli::OutFormat::Bin => {
-            binary_parser::parse_wrapper(bin_standard, input, output, ranks, bin_out_writer::new_writer(output, precision)).unwrap();
+            parse_with_writer(bin_standard, input, output, ranks, bin_out_writer::new_writer(output, precision), options)
         }
     }
 }
 
-fn parse_diff(error: f64, file1: String, file2: String, periodic_checking: bool, sim_box: &Vec<f64>) {
-    let error_limit: f64 = error;
-    let file1: &str = file1.as_str();
-    let file2: &str = file2.as_str();
+// if `cna` of options is true, the structure type of atoms is identified and written by the writer as an extra column.
+// `sim_box` of options is passed to the writer if it is specified.
+fn parse_with_writer(bin_standard: cli::FormatStandard, input: &str, output: &str, ranks: u32, mut writer: impl WriteProgress,
+                     options: &ConvertOptions) -> Result<(), binary_parser::ParseError> {
+    let (cna, sim_box, frame_range) = (options.cna, options.sim_box, options.frame_range);
+    if cna {
+        let mut writer = cna_out_writer::new_writer(writer);
+        if let Some(sim_box) = sim_box {
+            writer.set_sim_box(sim_box);
+        }
+        binary_parser::parse_wrapper(bin_standard, input, output, ranks, writer, frame_range).map(|_| ())
+    } else {
+        if let Some(sim_box) = sim_box {
+            writer.set_sim_box(sim_box);
+        }
+        binary_parser::parse_wrapper(bin_standard, input, output, ranks, writer, frame_range).map(|_| ())
+    }
+}
 
-    let mut box_measured_size = (0.0, 0.0, 0.0);
-    if !sim_box.is_empty() && sim_box.len() == 3 {
-        box_measured_size = (sim_box[0], sim_box[1], sim_box[2]);
+fn parse_rdf(input: &[PathBuf], output: &str, config: traj::frames::InputConfig, cutoff: f64, bins: usize,
+             sim_box: Option<SimBox>, verbose: bool) -> Result<(), String> {
+    if cutoff <= 0.0 || bins == 0 {
+        return Err(String::from("the cutoff and bins number must be positive."));
     }
+    let inputs: Vec<String> = input.iter().map(|path| path.to_string_lossy().to_string()).collect();
+    traj::rdf::rdf_wrapper(&inputs, output, config, cutoff, bins, sim_box, verbose)?;
+    println!("g(r) saved at {}", output);
+    Ok(())
+}
+
+// input files config of trajectory analyses, ranks is required by binary files of `current` standard.
+fn input_config(format: cli::InputFormat, standard: cli::FormatStandard, ranks: Option<usize>) -> Result<traj::frames::InputConfig, String> {
+    let ranks = match (format, standard, ranks) {
+        (cli::InputFormat::Bin, cli::FormatStandard::Current, None) | (cli::InputFormat::Bin, cli::FormatStandard::Current, Some(0)) => {
+            return Err(String::from("ranks must be specified for binary files of `current` standard."));
+        }
+        (_, _, ranks) => ranks.unwrap_or(0) as u32,
+    };
+    Ok(traj::frames::InputConfig { format, standard, ranks })
+}
 
-    diff::diff::diff_wrapper(file1, file2, error_limit, periodic_checking, box_measured_size);
+// print metadata of a binary file and return the exit code: 1 if the file can not be read, 2 if there are warnings.
+fn parse_info(input: &str, standard: cli::FormatStandard, ranks: Option<usize>, json: bool, output: Option<&str>) -> i32 {
+    let info = match input_config(cli::InputFormat::Bin, standard, ranks)
+        .and_then(|config| file_info::read_info(standard, input, config.ranks)) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let written = match output {
+        Some(path) => File::create(path).and_then(|mut file| file_info::write_info(&mut file, input, &info, json)),
+        None => file_info::write_info(&mut std::io::stdout(), input, &info, json),
+    };
+    if let Err(e) = written {
+        eprintln!("failed to write metadata: {}", e);
+        return 1;
+    }
+    if info.warnings.is_empty() { 0 } else { 2 }
+}
+
+// make simulation box from `--box` (3 box lengths or 9 values of cell vectors) and `--box-origin` options.
+fn make_sim_box(values: &[f64], origin: &[f64]) -> Result<Option<SimBox>, String> {
+    let origin = if origin.is_empty() { [0.0; 3] } else { [origin[0], origin[1], origin[2]] };
+    let sim_box = match values.len() {
+        0 => return Ok(None),
+        3 => SimBox::orthogonal((values[0], values[1], values[2]), origin),
+        9 => SimBox::new([[values[0], values[1], values[2]], [values[3], values[4], values[5]],
+                             [values[6], values[7], values[8]]], origin, [true; 3]),
+        _ => return Err(String::from("the box must be 3 box lengths or 9 values of cell vectors.")),
+    };
+    match sim_box {
+        Some(sim_box) => Ok(Some(sim_box)),
+        None => Err(String::from("the cell vectors of the box are singular.")),
+    }
+}
+
+// compare two files and return the exit code.
+// `binary` is the standard and ranks of binary files, or `None` for xyz files.
+fn parse_diff(file1: &str, file2: &str, binary: Option<(cli::FormatStandard, u32)>, config: &diff::diff::DiffConfig,
+              report_format: Option<cli::ReportFormat>, report_output: Option<&str>) -> i32 {
+    let result = match binary {
+        Some((bin_standard, ranks)) => diff::diff::binary_diff_wrapper(file1, file2, bin_standard, ranks, config),
+        None => diff::diff::diff_wrapper(file1, file2, config),
+    };
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return e.exit_code();
+        }
+    };
+
+    if let Some(format) = report_format {
+        let written = match report_output {
+            Some(path) => File::create(path)
+                .and_then(|mut file| diff::report::write_report(&mut file, format, &report)),
+            None => diff::report::write_report(&mut std::io::stdout(), format, &report),
+        };
+        if let Err(e) = written {
+            eprintln!("write diff report error: {}", e);
+            return diff::diff::EXIT_IO_ERROR;
+        }
+    }
+    report.exit_code()
 }
@@ -0,0 +1,136 @@
+// Per-atom displacement between a reference frame and the following frames, e.g. for finding
+// replacement collision sequences in cascades.
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::conv::binary_types::TypeAtom;
+use crate::conv::v2_atom_types::MASK_POSITION;
+use crate::conv::writers::out_writer::WriteProgress;
+use crate::traj::frames::{self, Frame, InputConfig};
+use crate::xyz::sim_box::SimBox;
+
+// extra columns written for each atom, the last column is 1 if the atom is displaced beyond the threshold.
+const COLUMNS: [&str; 5] = ["dx", "dy", "dz", "displacement", "displaced"];
+
+/**
+ * displacement vector of each atom in `frame` from the atom with the same id in `reference`,
+ * under minimum image convention if `sim_box` is not `None`.
+ * `None` is given if the atom is not in the reference frame.
+ */
+pub fn displacements(reference: &HashMap<u64, [f64; 3]>, frame: &Frame, sim_box: Option<SimBox>) -> Vec<Option<[f64; 3]>> {
+    (0..frame.atoms.len()).into_par_iter().map(|i| {
+        let r0 = reference.get(&frame.atom_id(i))?;
+        let r = frame.atoms[i].position;
+        let d = [r[0] - r0[0], r[1] - r0[1], r[2] - r0[2]];
+        Some(match sim_box {
+            Some(sim_box) => sim_box.minimum_image(d),
+            None => d,
+        })
+    }).collect()
+}
+
+// atom in frame, with the type for writer: the type of Fe, Cu or Ni, otherwise, the element index (start from 3) in frame.
+fn to_type_atom(frame: &Frame, index: usize) -> TypeAtom {
+    let atom = &frame.atoms[index];
+    let tp = TypeAtom::get_type_by_ele_name(&frame.elements[atom.element]).unwrap_or(3 + atom.element as i32);
+    TypeAtom {
+        id: frame.atom_id(index),
+        tp,
+        inter_type: 0,
+        atom_location: atom.position,
+        atom_velocity: [0.0; 3],
+        atom_force: [0.0; 3],
+    }
+}
+
+/**
+ * compute displacements of atoms in frames after the `reference_frame` of the input file,
+ * and write atoms of these frames with the displacement columns by `writer` (e.g. dump writer).
+ * Atoms whose displacement is larger than `threshold` are flagged in column `displaced`.
+ * `sim_box` is the box specified on command line, it overrides the box saved in the file.
+ */
+pub fn displacement_wrapper<W: WriteProgress>(input: &str, output: &str, config: InputConfig, reference_frame: usize,
+                                              threshold: f64, sim_box: Option<SimBox>, mut writer: W) -> Result<(), String> {
+    let mut reference: Option<HashMap<u64, [f64; 3]>> = None;
+    let mut warned = false;
+    writer.set_extra_columns(&COLUMNS);
+    if let Some(sim_box) = sim_box {
+        writer.set_sim_box(sim_box);
+    }
+    writer.on_start(output, MASK_POSITION);
+    for (index, frame) in frames::open_frames(input, config)?.enumerate() {
+        if index < reference_frame {
+            continue;
+        }
+        let frame = frame?;
+        if reference.is_none() {
+            reference = Some((0..frame.atoms.len()).map(|i| (frame.atom_id(i), frame.atoms[i].position)).collect());
+            continue;
+        }
+        let frame_box = sim_box.or(frame.sim_box);
+        if frame_box.is_none() && !warned {
+            println!("Warning: simulation box is not found in input file, minimum image convention is not applied.");
+            warned = true;
+        }
+
+        let displacements = displacements(reference.as_ref().unwrap(), &frame, frame_box);
+        let mut displaced = 0;
+        let mut max_displacement: f64 = 0.0;
+        writer.before_frame(index as u32, output);
+        if let Some(step) = frame.step {
+            writer.set_frame_step(step, frame.time);
+        }
+        for (i, d) in displacements.iter().enumerate() {
+            let d = match d {
+                Some(d) => d,
+                None => continue,
+            };
+            let magnitude = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+            let flag = magnitude > threshold;
+            if flag {
+                displaced += 1;
+            }
+            max_displacement = max_displacement.max(magnitude);
+            let extra = [format!("{:.6}", d[0]), format!("{:.6}", d[1]), format!("{:.6}", d[2]),
+                format!("{:.6}", magnitude), (flag as u8).to_string()];
+            writer.on_atom_read_with_extra(&to_type_atom(&frame, i), &extra);
+        }
+        writer.after_frame();
+        println!("frame {} (step {}): {} atoms displaced beyond {}, max displacement: {}", index,
+                 frame.step.map_or(String::from("-"), |s| s.to_string()), displaced, threshold, max_displacement);
+    }
+    writer.done();
+    if reference.is_none() {
+        return Err(format!("reference frame {} is not found in input file {}", reference_frame, input));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_displacements() {
+        let mut reference = Frame::new(None, None, None);
+        reference.push(Some(1), "Fe", [0.5, 5.0, 5.0]);
+        reference.push(Some(2), "Cu", [5.0, 5.0, 5.0]);
+        let reference: HashMap<u64, [f64; 3]> = (0..2).map(|i| (reference.atom_id(i), reference.atoms[i].position)).collect();
+
+        let mut frame = Frame::new(None, None, None);
+        frame.push(Some(2), "Cu", [5.0, 5.0, 6.0]);
+        frame.push(Some(1), "Fe", [7.5, 5.0, 5.0]);
+        frame.push(Some(3), "Fe", [1.0, 1.0, 1.0]);
+        let sim_box = SimBox::orthogonal((8.0, 8.0, 8.0), [0.0; 3]);
+        assert_eq!(displacements(&reference, &frame, sim_box), vec![Some([0.0, 0.0, 1.0]), Some([-1.0, 0.0, 0.0]), None]);
+        // without periodic boundaries.
+        assert_eq!(displacements(&reference, &frame, None)[1], Some([7.0, 0.0, 0.0]));
+
+        assert_eq!(to_type_atom(&frame, 0).tp, 1);
+        frame.push(None, "W", [0.0; 3]);
+        let atom = to_type_atom(&frame, 3);
+        assert_eq!((atom.id, atom.tp), (3, 5));
+    }
+}
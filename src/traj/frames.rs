@@ -0,0 +1,121 @@
+// Frames of a trajectory read from xyz, LAMMPS dump or binary files, for the trajectory analyses.
+
+use std::fs::File;
+use std::io::BufReader;
+
+use xyzio::Atom;
+
+use crate::cli;
+use crate::conv::binary_parser;
+use crate::conv::binary_types::TypeAtom;
+use crate::xyz::dump_reader::DumpReader;
+use crate::xyz::sim_box::SimBox;
+use crate::xyz::xyz_reader::Reader;
+
+pub struct FrameAtom {
+    // atom id, `None` if the input file does not save atom ids (e.g. xyz files).
+    pub id: Option<u64>,
+    // index of the element name in `Frame::elements`.
+    pub element: usize,
+    pub position: [f64; 3],
+}
+
+pub struct Frame {
+    // step in frame header, it is available for dump files and v2 binary files.
+    pub step: Option<u64>,
+    // time in frame header, it is available for v2 binary files (and dump files with time).
+    pub time: Option<f64>,
+    // the simulation box saved in the file (extxyz and dump files).
+    pub sim_box: Option<SimBox>,
+    // element names of atoms in this frame.
+    pub elements: Vec<String>,
+    pub atoms: Vec<FrameAtom>,
+}
+
+impl Frame {
+    pub fn new(step: Option<u64>, time: Option<f64>, sim_box: Option<SimBox>) -> Frame {
+        Frame { step, time, sim_box, elements: Vec::new(), atoms: Vec::new() }
+    }
+
+    pub fn push(&mut self, id: Option<u64>, element: &str, position: [f64; 3]) {
+        let element = match self.elements.iter().position(|e| e == element) {
+            Some(index) => index,
+            None => {
+                self.elements.push(element.to_string());
+                self.elements.len() - 1
+            }
+        };
+        self.atoms.push(FrameAtom { id, element, position });
+    }
+
+    // id of the atom, or the atom index if ids are not saved in the file.
+    pub fn atom_id(&self, index: usize) -> u64 {
+        self.atoms[index].id.unwrap_or(index as u64)
+    }
+
+    pub fn positions(&self) -> Vec<[f64; 3]> {
+        self.atoms.iter().map(|atom| atom.position).collect()
+    }
+
+    fn from_type_atoms(step: Option<u64>, time: Option<f64>, sim_box: Option<SimBox>, atoms: &[TypeAtom]) -> Frame {
+        let mut frame = Frame::new(step, time, sim_box);
+        for atom in atoms {
+            frame.push(Some(atom.id), atom.get_name_by_ele_name(), atom.atom_location);
+        }
+        frame
+    }
+}
+
+pub type Frames = Box<dyn Iterator<Item=Result<Frame, String>>>;
+
+// format of input files.
+#[derive(Copy, Clone)]
+pub struct InputConfig {
+    pub format: cli::InputFormat,
+    // binary file standard and ranks, used by binary files.
+    pub standard: cli::FormatStandard,
+    pub ranks: u32,
+}
+
+/**
+ * open the input file and read it frame by frame.
+ * For xyz files, the simulation box is read from the `Lattice=` header of extxyz file;
+ * for binary files, the box is not available.
+ */
+pub fn open_frames(input: &str, config: InputConfig) -> Result<Frames, String> {
+    match config.format {
+        cli::InputFormat::Xyz => {
+            let file = File::open(input).map_err(|e| format!("open file {} error: {}", input, e))?;
+            let frames = Reader::new(file).into_frames::<Atom>().enumerate().map(|(frame, snapshot)| match snapshot {
+                Ok(snapshot) => {
+                    let mut frame = Frame::new(None, None, SimBox::from_extxyz_comment(&snapshot.comment));
+                    for atom in &snapshot.atoms {
+                        frame.push(None, &atom.element, [atom.x as f64, atom.y as f64, atom.z as f64]);
+                    }
+                    Ok(frame)
+                }
+                Err(e) => Err(format!("read input xyz file error at frame {}: {}", frame, e)),
+            });
+            Ok(Box::new(frames))
+        }
+        cli::InputFormat::Dump => {
+            let file = File::open(input).map_err(|e| format!("open file {} error: {}", input, e))?;
+            let frames = DumpReader::new(BufReader::new(file)).map(|frame| {
+                frame.map(|frame| Frame::from_type_atoms(Some(frame.timestep), frame.time, frame.sim_box, &frame.atoms))
+            });
+            Ok(Box::new(frames))
+        }
+        cli::InputFormat::Bin => {
+            let mut reader = binary_parser::make_frame_reader(config.standard, input, config.ranks)
+                .map_err(|e| format!("read input binary file {} error: {}", input, e))?;
+            let mut frame: u32 = 0;
+            let frames = std::iter::from_fn(move || {
+                let header = reader.frame_header(frame);
+                let atoms = reader.next()?;
+                frame += 1;
+                Some(Ok(Frame::from_type_atoms(header.map(|h| h.step), header.map(|h| h.time), None, &atoms)))
+            });
+            Ok(Box::new(frames))
+        }
+    }
+}
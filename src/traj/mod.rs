@@ -0,0 +1,4 @@
+pub(crate) mod frames;
+pub(crate) mod rdf;
+pub(crate) mod msd;
+pub(crate) mod displacement;
@@ -0,0 +1,244 @@
+// Mean squared displacement (MSD) of atoms over frames, and the diffusion coefficient fitted by Einstein relation.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::json;
+use crate::traj::frames::{self, Frame, InputConfig};
+use crate::xyz::sim_box::SimBox;
+
+// MSD of a frame, of all atoms and atoms of each element.
+pub struct MsdPoint {
+    pub frame: usize,
+    pub step: Option<u64>,
+    pub time: f64,
+    pub total: f64,
+    // indexed by `MsdTracker::elements`.
+    pub elements: Vec<f64>,
+}
+
+/**
+ * Atoms in the reference (first) frame are tracked by id in the following frames
+ * (by the atom index if the file does not save atom ids, e.g. xyz files).
+ * Atoms are unwrapped by the minimum image of displacement between two adjacent frames,
+ * thus, atoms crossing periodic boundaries are tracked correctly if they move less than half box in a frame.
+ */
+pub struct MsdTracker {
+    ids: HashMap<u64, usize>,
+    // element names of tracked atoms, in sorted order.
+    pub elements: Vec<String>,
+    atom_elements: Vec<usize>,
+    reference: Vec<[f64; 3]>,
+    previous: Vec<[f64; 3]>,
+    unwrapped: Vec<[f64; 3]>,
+    pub series: Vec<MsdPoint>,
+}
+
+impl MsdTracker {
+    pub fn new(reference: &Frame) -> MsdTracker {
+        let mut elements = reference.elements.clone();
+        elements.sort();
+        // element index in reference frame -> index in sorted elements.
+        let element_map: Vec<usize> = reference.elements.iter()
+            .map(|e| elements.iter().position(|s| s == e).unwrap())
+            .collect();
+        let mut ids = HashMap::with_capacity(reference.atoms.len());
+        for i in 0..reference.atoms.len() {
+            ids.insert(reference.atom_id(i), i);
+        }
+        let positions = reference.positions();
+        MsdTracker {
+            ids,
+            elements,
+            atom_elements: reference.atoms.iter().map(|atom| element_map[atom.element]).collect(),
+            reference: positions.clone(),
+            previous: positions.clone(),
+            unwrapped: positions,
+            series: Vec::new(),
+        }
+    }
+
+    /**
+     * add a frame whose time is `time`, and compute MSD of the frame.
+     * `sim_box` is used for unwrapping periodic crossings, if it is `None`, atoms are not unwrapped.
+     * Atoms not in the reference frame are ignored, and atoms missing in this frame keep their last positions.
+     */
+    pub fn add_frame(&mut self, frame: &Frame, sim_box: Option<SimBox>, time: f64) {
+        for (i, atom) in frame.atoms.iter().enumerate() {
+            let k = match self.ids.get(&frame.atom_id(i)) {
+                Some(&k) => k,
+                None => continue,
+            };
+            let prev = self.previous[k];
+            let mut d = [atom.position[0] - prev[0], atom.position[1] - prev[1], atom.position[2] - prev[2]];
+            if let Some(sim_box) = sim_box {
+                d = sim_box.minimum_image(d);
+            }
+            for (u, d) in self.unwrapped[k].iter_mut().zip(d.iter()) {
+                *u += d;
+            }
+            self.previous[k] = atom.position;
+        }
+
+        let mut sums = vec![0.0; self.elements.len()];
+        let mut counts = vec![0_usize; self.elements.len()];
+        for ((r, r0), &e) in self.unwrapped.iter().zip(&self.reference).zip(&self.atom_elements) {
+            sums[e] += (r[0] - r0[0]).powi(2) + (r[1] - r0[1]).powi(2) + (r[2] - r0[2]).powi(2);
+            counts[e] += 1;
+        }
+        let atoms = self.unwrapped.len().max(1) as f64;
+        self.series.push(MsdPoint {
+            frame: self.series.len(),
+            step: frame.step,
+            time,
+            total: sums.iter().sum::<f64>() / atoms,
+            elements: sums.iter().zip(&counts).map(|(s, &n)| s / n.max(1) as f64).collect(),
+        });
+    }
+
+    // diffusion coefficient of all atoms and atoms of each element.
+    pub fn diffusion_coefficients(&self) -> (Option<f64>, Vec<Option<f64>>) {
+        let times: Vec<f64> = self.series.iter().map(|p| p.time).collect();
+        let total: Vec<f64> = self.series.iter().map(|p| p.total).collect();
+        let elements = (0..self.elements.len()).map(|e| {
+            let msd: Vec<f64> = self.series.iter().map(|p| p.elements[e]).collect();
+            diffusion_coefficient(&times, &msd)
+        }).collect();
+        (diffusion_coefficient(&times, &total), elements)
+    }
+
+    // write columns: frame, step, time, MSD of all atoms and MSD of each element.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "# frame step time total {}", self.elements.join(" "))?;
+        for point in &self.series {
+            write!(writer, "{} {} {} {:.6}", point.frame, point.step.map_or(String::from("-"), |s| s.to_string()),
+                   point.time, point.total)?;
+            for msd in &point.elements {
+                write!(writer, " {:.6}", msd)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json_summary<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let (total, elements) = self.diffusion_coefficients();
+        let mut coefficients = vec![("total", json::optional(total.map(json::number)))];
+        for (e, d) in self.elements.iter().zip(elements) {
+            coefficients.push((e.as_str(), json::optional(d.map(json::number))));
+        }
+        let items = vec![
+            ("atoms", self.reference.len().to_string()),
+            ("frames", self.series.len().to_string()),
+            ("diffusion_coefficient", json::object(coefficients)),
+        ];
+        writeln!(writer, "{}", json::pretty_object(items, 0))
+    }
+}
+
+/**
+ * diffusion coefficient by Einstein relation: MSD(t) = 6Dt, where the slope is fitted by linear least squares.
+ * `None` is returned if there are less than 2 distinct time points.
+ */
+pub fn diffusion_coefficient(times: &[f64], msd: &[f64]) -> Option<f64> {
+    let n = times.len() as f64;
+    if times.len() < 2 {
+        return None;
+    }
+    let (t_mean, m_mean) = (times.iter().sum::<f64>() / n, msd.iter().sum::<f64>() / n);
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (t, m) in times.iter().zip(msd) {
+        covariance += (t - t_mean) * (m - m_mean);
+        variance += (t - t_mean) * (t - t_mean);
+    }
+    if variance == 0.0 {
+        return None;
+    }
+    Some(covariance / variance / 6.0)
+}
+
+/**
+ * compute MSD over all frames of the input file, and write it to `output` as columnar text file,
+ * and the diffusion coefficients to the json summary file.
+ * The time of a frame is the time in frame header (v2 binary files), or step (or frame index) multiplied by `dt`.
+ * `sim_box` is the box specified on command line, it overrides the box saved in the file.
+ */
+pub fn msd_wrapper(input: &str, output: &str, config: InputConfig, dt: f64, sim_box: Option<SimBox>,
+                   verbose: bool) -> Result<(), String> {
+    let mut tracker: Option<MsdTracker> = None;
+    for (index, frame) in frames::open_frames(input, config)?.enumerate() {
+        let frame = frame?;
+        let frame_box = sim_box.or(frame.sim_box);
+        if index == 0 && frame_box.is_none() {
+            println!("Warning: simulation box is not found in input file, periodic crossings of atoms are not unwrapped.");
+        }
+        let time = frame.time.unwrap_or_else(|| frame.step.unwrap_or(index as u64) as f64 * dt);
+        let tracker = tracker.get_or_insert_with(|| MsdTracker::new(&frame));
+        tracker.add_frame(&frame, frame_box, time);
+        if verbose {
+            println!("frame {}: time {}, MSD {}", index, time, tracker.series[index].total);
+        }
+    }
+    let tracker = tracker.ok_or_else(|| format!("no frame in input file {}", input))?;
+    match tracker.diffusion_coefficients().0 {
+        Some(d) => println!("diffusion coefficient: {}", d),
+        None => println!("Warning: diffusion coefficient is not available, at least 2 frames with different time are required."),
+    }
+
+    let path = Path::new(output);
+    let write = |path: &Path, json: bool| -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if json {
+            tracker.write_json_summary(&mut writer)?;
+        } else {
+            tracker.write(&mut writer)?;
+        }
+        writer.flush()
+    };
+    write(path, false).map_err(|e| format!("write file {} error: {}", path.display(), e))?;
+    let summary_path = json::summary_path(path);
+    write(&summary_path, true).map_err(|e| format!("write file {} error: {}", summary_path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msd() {
+        let sim_box = SimBox::orthogonal((10.0, 10.0, 10.0), [0.0; 3]);
+        // Fe atom moves 3.0 along x in each frame, and crosses the periodic boundary; Cu atom does not move.
+        let make_frame = |k: usize| {
+            let mut frame = Frame::new(Some(k as u64 * 100), None, sim_box);
+            frame.push(Some(2), "Cu", [5.0, 5.0, 5.0]);
+            frame.push(Some(1), "Fe", [(1.0 + 3.0 * k as f64) % 10.0, 5.0, 5.0]);
+            frame
+        };
+        let mut tracker = MsdTracker::new(&make_frame(0));
+        for k in 0..5 {
+            // atoms are matched by id, not the order.
+            let mut frame = make_frame(k);
+            frame.atoms.reverse();
+            tracker.add_frame(&frame, sim_box, k as f64);
+        }
+        assert_eq!(tracker.elements, vec![String::from("Cu"), String::from("Fe")]);
+        let last = &tracker.series[4];
+        assert_eq!(last.step, Some(400));
+        assert!((last.elements[1] - 144.0).abs() < 1e-9);
+        assert_eq!(last.elements[0], 0.0);
+        assert!((last.total - 72.0).abs() < 1e-9);
+
+        // MSD(t) = 6Dt
+        let times = [0.0, 1.0, 2.0, 3.0];
+        assert!((diffusion_coefficient(&times, &[0.0, 1.2, 2.4, 3.6]).unwrap() - 0.2).abs() < 1e-12);
+        assert_eq!(diffusion_coefficient(&[1.0], &[0.0]), None);
+
+        let mut out: Vec<u8> = Vec::new();
+        tracker.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("# frame step time total Cu Fe\n0 0 0 0.000000 0.000000 0.000000\n"));
+    }
+}
@@ -0,0 +1,215 @@
+// Radial distribution function g(r), total and partial g(r) of each element pair, averaged over frames.
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use rayon::prelude::*;
+
+use crate::ans::neighbor::{self, CellList};
+use crate::traj::frames::{self, Frame, InputConfig};
+use crate::xyz::sim_box::SimBox;
+
+/**
+ * g(r) of each frame is accumulated, the result is the average over frames.
+ * A partial g(r) is only averaged over the frames containing both elements of the pair.
+ */
+pub struct Rdf {
+    cutoff: f64,
+    bins: usize,
+    frames: usize,
+    total: Vec<f64>,
+    // (element a, element b) with a <= b -> (sum of g(r), frames number).
+    partials: BTreeMap<(String, String), (Vec<f64>, usize)>,
+}
+
+impl Rdf {
+    pub fn new(cutoff: f64, bins: usize) -> Rdf {
+        Rdf { cutoff, bins, frames: 0, total: vec![0.0; bins], partials: BTreeMap::new() }
+    }
+
+    /**
+     * count pairs of atoms within cutoff in the simulation box, and accumulate g(r) of this frame.
+     * For element pair (a, b): g_ab(r) = V * n_ab(r) / (N_a * N_b * 4/3 * pi * (r_hi^3 - r_lo^3)),
+     * where n_ab(r) is the number of b atoms in the shell around all a atoms.
+     */
+    pub fn add_frame(&mut self, frame: &Frame, sim_box: SimBox) {
+        let types = frame.elements.len();
+        if frame.atoms.is_empty() {
+            return;
+        }
+        let bins = self.bins;
+        let width = self.cutoff / bins as f64;
+        let cell_list = CellList::new(&frame.positions(), sim_box, self.cutoff);
+        // histogram of each ordered element pair (a, b): index (a * types + b) * bins + bin.
+        let histogram = (0..frame.atoms.len()).into_par_iter()
+            .fold(|| vec![0_u64; types * types * bins], |mut histogram, i| {
+                let a = frame.atoms[i].element;
+                for (j, d) in cell_list.neighbors(i) {
+                    let bin = ((d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt() / width) as usize;
+                    if bin < bins {
+                        histogram[(a * types + frame.atoms[j].element) * bins + bin] += 1;
+                    }
+                }
+                histogram
+            })
+            .reduce(|| vec![0_u64; types * types * bins], |mut a, b| {
+                for (x, y) in a.iter_mut().zip(b) {
+                    *x += y;
+                }
+                a
+            });
+
+        let mut counts = vec![0_usize; types];
+        for atom in &frame.atoms {
+            counts[atom.element] += 1;
+        }
+        let volume = sim_box.volume();
+        let shell = |bin: usize| 4.0 / 3.0 * PI * (((bin + 1) as f64 * width).powi(3) - (bin as f64 * width).powi(3));
+        let n = frame.atoms.len() as f64;
+        for bin in 0..bins {
+            let pairs: u64 = (0..types * types).map(|ab| histogram[ab * bins + bin]).sum();
+            self.total[bin] += volume * pairs as f64 / (n * n * shell(bin));
+        }
+        for a in 0..types {
+            for b in 0..types {
+                let (name_a, name_b) = (&frame.elements[a], &frame.elements[b]);
+                if name_a > name_b {
+                    continue;
+                }
+                let (sum, frames) = self.partials.entry((name_a.clone(), name_b.clone()))
+                    .or_insert_with(|| (vec![0.0; bins], 0));
+                let norm = (counts[a] * counts[b]) as f64;
+                for (bin, g) in sum.iter_mut().enumerate() {
+                    *g += volume * histogram[(a * types + b) * bins + bin] as f64 / (norm * shell(bin));
+                }
+                *frames += 1;
+            }
+        }
+        self.frames += 1;
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    // write columns: r (center of bin), total g(r) and partial g(r) of each element pair.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let pairs: Vec<String> = self.partials.keys().map(|(a, b)| format!("{}-{}", a, b)).collect();
+        writeln!(writer, "# r total {}", pairs.join(" "))?;
+        let width = self.cutoff / self.bins as f64;
+        let average = |sum: f64, frames: usize| if frames == 0 { 0.0 } else { sum / frames as f64 };
+        for bin in 0..self.bins {
+            write!(writer, "{:.6} {:.6}", (bin as f64 + 0.5) * width, average(self.total[bin], self.frames))?;
+            for (sum, frames) in self.partials.values() {
+                write!(writer, " {:.6}", average(sum[bin], *frames))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * the simulation box of a frame: the box specified on command line, or the box saved in the file,
+ * otherwise, a box with free boundaries covering all atoms (the density, thus g(r), is approximate).
+ */
+pub fn frame_box(frame: &Frame, specified: Option<SimBox>) -> Option<SimBox> {
+    specified.or(frame.sim_box).or_else(|| neighbor::bounding_box(&frame.positions()))
+}
+
+/**
+ * compute g(r) averaged over all frames in all input files, and write it to `output` as columnar text file.
+ * `sim_box` is the box specified on command line, it overrides the box saved in the files.
+ */
+pub fn rdf_wrapper(inputs: &[String], output: &str, config: InputConfig, cutoff: f64, bins: usize,
+                   sim_box: Option<SimBox>, verbose: bool) -> Result<(), String> {
+    let mut rdf = Rdf::new(cutoff, bins);
+    let mut warned = false;
+    for input in inputs {
+        println!("reading file {}", input);
+        for frame in frames::open_frames(input, config)? {
+            let frame = frame?;
+            if sim_box.is_none() && frame.sim_box.is_none() && !warned {
+                println!("Warning: simulation box is not found in input file, a box with free boundaries covering all atoms is used.");
+                warned = true;
+            }
+            if let Some(frame_box) = frame_box(&frame, sim_box) {
+                rdf.add_frame(&frame, frame_box);
+            }
+            if verbose {
+                println!("frame {} (step {}): {} atoms", rdf.frames(), frame.step.map_or(String::from("-"), |s| s.to_string()),
+                         frame.atoms.len());
+            }
+        }
+    }
+    if rdf.frames() == 0 {
+        return Err(String::from("no frame in input files"));
+    }
+    println!("g(r) is averaged over {} frames", rdf.frames());
+
+    let file = File::create(output).map_err(|e| format!("create file {} error: {}", output, e))?;
+    let mut writer = BufWriter::new(file);
+    rdf.write(&mut writer).and_then(|_| writer.flush()).map_err(|e| format!("write file {} error: {}", output, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // B2 lattice (CsCl structure) of Fe and Cu: Fe at cell corners and Cu at body centers.
+    fn b2_frame(a: f64, n: usize) -> (Frame, SimBox) {
+        let mut frame = Frame::new(None, None, None);
+        for z in 0..n {
+            for y in 0..n {
+                for x in 0..n {
+                    let r = [x as f64 * a, y as f64 * a, z as f64 * a];
+                    frame.push(None, "Fe", r);
+                    frame.push(None, "Cu", [r[0] + a / 2.0, r[1] + a / 2.0, r[2] + a / 2.0]);
+                }
+            }
+        }
+        let l = a * n as f64;
+        (frame, SimBox::orthogonal((l, l, l), [0.0; 3]).unwrap())
+    }
+
+    // coordination number: integral of rho * g(r) * 4 * pi * r^2 dr in [0, r_max).
+    fn coordination(g: &[f64], width: f64, density: f64, r_max: f64) -> f64 {
+        g.iter().enumerate().take_while(|(bin, _)| (*bin as f64 + 1.0) * width <= r_max)
+            .map(|(bin, g)| density * g * 4.0 / 3.0 * PI * (((bin + 1) as f64 * width).powi(3) - (bin as f64 * width).powi(3)))
+            .sum()
+    }
+
+    #[test]
+    fn test_rdf() {
+        let a = 2.855;
+        let (frame, sim_box) = b2_frame(a, 4);
+        let mut rdf = Rdf::new(4.0, 100);
+        rdf.add_frame(&frame, sim_box);
+        rdf.add_frame(&frame, sim_box);
+        assert_eq!(rdf.frames(), 2);
+        let width = 0.04;
+        let first_shell = (3.0_f64.sqrt() / 2.0 + 1.0) / 2.0 * a;
+        let total: Vec<f64> = rdf.total.iter().map(|g| g / 2.0).collect();
+        let density = frame.atoms.len() as f64 / sim_box.volume();
+        assert!((coordination(&total, width, density, first_shell) - 8.0).abs() < 1e-9);
+
+        // each Fe atom has 8 Cu neighbors and no Fe neighbors in the first shell.
+        let (fe_cu, frames) = &rdf.partials[&(String::from("Cu"), String::from("Fe"))];
+        assert_eq!(*frames, 2);
+        let fe_cu: Vec<f64> = fe_cu.iter().map(|g| g / 2.0).collect();
+        assert!((coordination(&fe_cu, width, density / 2.0, first_shell) - 8.0).abs() < 1e-9);
+        let (fe_fe, _) = &rdf.partials[&(String::from("Fe"), String::from("Fe"))];
+        assert_eq!(coordination(fe_fe, width, density / 2.0, first_shell), 0.0);
+        // the 2nd shell of Fe atoms: 6 Fe atoms at lattice constant.
+        let fe_fe: Vec<f64> = fe_fe.iter().map(|g| g / 2.0).collect();
+        assert!((coordination(&fe_fe, width, density / 2.0, 1.1 * a) - 6.0).abs() < 1e-9);
+
+        let mut out: Vec<u8> = Vec::new();
+        rdf.write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("# r total Cu-Cu Cu-Fe Fe-Fe\n0.020000 0.000000 0.000000 0.000000 0.000000\n"));
+        assert_eq!(out.lines().count(), 101);
+    }
+}
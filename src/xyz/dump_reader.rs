@@ -0,0 +1,210 @@
+// Reader of LAMMPS dump (text) files, e.g. files written by `conv -f dump`.
+// see also: https://docs.lammps.org/dump.html
+
+use std::io::{self, BufRead};
+
+use crate::conv::binary_types::TypeAtom;
+use crate::xyz::sim_box::SimBox;
+
+// a frame in dump file.
+pub struct DumpFrame {
+    pub timestep: u64,
+    // time of the frame, it is written by `dump_modify time yes` of LAMMPS, or by `conv` if it is available.
+    pub time: Option<f64>,
+    pub sim_box: Option<SimBox>,
+    // atoms with id, type and position, the velocity and force are not read.
+    pub atoms: Vec<TypeAtom>,
+}
+
+pub struct DumpReader<R: BufRead> {
+    lines: io::Lines<R>,
+    line_no: usize,
+}
+
+impl<R: BufRead> DumpReader<R> {
+    pub fn new(inner: R) -> Self {
+        DumpReader { lines: inner.lines(), line_no: 0 }
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, String> {
+        match self.lines.next() {
+            None => Ok(None),
+            Some(Ok(line)) => {
+                self.line_no += 1;
+                Ok(Some(line))
+            }
+            Some(Err(e)) => Err(format!("read dump file error: {}", e)),
+        }
+    }
+
+    fn expect_line(&mut self) -> Result<String, String> {
+        self.next_line()?.ok_or_else(|| format!("unexpected end of dump file at line {}", self.line_no + 1))
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("{} at line {} of dump file", message, self.line_no))
+    }
+
+    // read a frame, `None` is returned if it reaches the end of file.
+    pub fn read_frame(&mut self) -> Result<Option<DumpFrame>, String> {
+        let mut timestep = 0;
+        let mut time = None;
+        let mut atoms_num: Option<usize> = None;
+        let mut sim_box = None;
+        loop {
+            let line = match self.next_line()? {
+                Some(line) => line,
+                None if atoms_num.is_none() => return Ok(None),
+                None => return self.error("missing atoms"),
+            };
+            let item = match line.trim().strip_prefix("ITEM:") {
+                Some(item) => item.trim().to_string(),
+                None if line.trim().is_empty() => continue,
+                None => return self.error("expect `ITEM:`"),
+            };
+            if item.starts_with("TIMESTEP") {
+                timestep = match self.expect_line()?.trim().parse() {
+                    Ok(step) => step,
+                    Err(_) => return self.error("bad timestep"),
+                };
+            } else if item == "TIME" {
+                time = match self.expect_line()?.trim().parse() {
+                    Ok(t) => Some(t),
+                    Err(_) => return self.error("bad time"),
+                };
+            } else if item.starts_with("NUMBER OF ATOMS") {
+                atoms_num = match self.expect_line()?.trim().parse() {
+                    Ok(num) => Some(num),
+                    Err(_) => return self.error("bad number of atoms"),
+                };
+            } else if let Some(flags) = item.strip_prefix("BOX BOUNDS") {
+                sim_box = Some(self.read_box(flags)?);
+            } else if let Some(columns) = item.strip_prefix("ATOMS") {
+                let atoms_num = match atoms_num {
+                    Some(num) => num,
+                    None => return self.error("missing number of atoms"),
+                };
+                let atoms = self.read_atoms(columns, atoms_num)?;
+                return Ok(Some(DumpFrame { timestep, time, sim_box, atoms }));
+            }
+        }
+    }
+
+    // read box bounds, `flags` is like `pp pp pp` (orthogonal box) or `xy xz yz pp pp pp` (triclinic box).
+    fn read_box(&mut self, flags: &str) -> Result<SimBox, String> {
+        let flags: Vec<&str> = flags.split_whitespace().collect();
+        let triclinic = flags.first() == Some(&"xy");
+        let boundaries = if triclinic { &flags[3..] } else { &flags[..] };
+        let mut pbc = [true; 3];
+        for (d, b) in boundaries.iter().take(3).enumerate() {
+            pbc[d] = *b == "pp";
+        }
+        let mut bounds = [[0.0; 3]; 3];
+        for bound in bounds.iter_mut() {
+            let line = self.expect_line()?;
+            let values: Result<Vec<f64>, _> = line.split_whitespace().map(|v| v.parse::<f64>()).collect();
+            match values {
+                Ok(values) if values.len() >= 2 => {
+                    bound[0] = values[0];
+                    bound[1] = values[1];
+                    bound[2] = values.get(2).cloned().unwrap_or(0.0);
+                }
+                _ => return self.error("bad box bounds"),
+            }
+        }
+        // for triclinic box, the bounds are the bounding box of the triclinic box.
+        let (xy, xz, yz) = (bounds[0][2], bounds[1][2], bounds[2][2]);
+        let x_lo = bounds[0][0] - 0.0_f64.min(xy).min(xz).min(xy + xz);
+        let x_hi = bounds[0][1] - 0.0_f64.max(xy).max(xz).max(xy + xz);
+        let y_lo = bounds[1][0] - 0.0_f64.min(yz);
+        let y_hi = bounds[1][1] - 0.0_f64.max(yz);
+        let cell = [[x_hi - x_lo, 0.0, 0.0], [xy, y_hi - y_lo, 0.0], [xz, yz, bounds[2][1] - bounds[2][0]]];
+        match SimBox::new(cell, [x_lo, y_lo, bounds[2][0]], pbc) {
+            Some(sim_box) => Ok(sim_box),
+            None => self.error("singular box"),
+        }
+    }
+
+    fn read_atoms(&mut self, columns: &str, atoms_num: usize) -> Result<Vec<TypeAtom>, String> {
+        let columns: Vec<&str> = columns.split_whitespace().collect();
+        let find = |names: &[&str]| columns.iter().position(|c| names.contains(c));
+        let id_col = find(&["id"]);
+        let type_col = find(&["type"]);
+        let pos_cols = match (find(&["x", "xu"]), find(&["y", "yu"]), find(&["z", "zu"])) {
+            (Some(x), Some(y), Some(z)) => [x, y, z],
+            _ => return self.error("missing position columns (x y z or xu yu zu)"),
+        };
+
+        let mut atoms = Vec::with_capacity(atoms_num);
+        for i in 0..atoms_num {
+            let line = self.expect_line()?;
+            let values: Vec<&str> = line.split_whitespace().collect();
+            if values.len() < columns.len() {
+                return self.error("too few columns");
+            }
+            let mut atom = TypeAtom {
+                id: i as u64,
+                tp: 0,
+                inter_type: 0,
+                atom_location: [0.0; 3],
+                atom_velocity: [0.0; 3],
+                atom_force: [0.0; 3],
+            };
+            if let Some(c) = id_col {
+                atom.id = match values[c].parse() {
+                    Ok(id) => id,
+                    Err(_) => return self.error("bad atom id"),
+                };
+            }
+            if let Some(c) = type_col {
+                atom.tp = match values[c].parse() {
+                    Ok(tp) => tp,
+                    Err(_) => return self.error("bad atom type"),
+                };
+            }
+            for (d, &c) in pos_cols.iter().enumerate() {
+                atom.atom_location[d] = match values[c].parse() {
+                    Ok(v) => v,
+                    Err(_) => return self.error("bad atom position"),
+                };
+            }
+            atoms.push(atom);
+        }
+        Ok(atoms)
+    }
+}
+
+impl<R: BufRead> Iterator for DumpReader<R> {
+    type Item = Result<DumpFrame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_dump() {
+        let data: &[u8] = b"ITEM: TIMESTEP\n100\nITEM: NUMBER OF ATOMS\n2\nITEM: BOX BOUNDS pp pp ff\n\
+        0.0 10.0\n-1.0 9.0\n0.0 5.0\nITEM: ATOMS id type x y z\n3 1 \t1.0 \t2.0 \t3.0\n7 0 \t4.0 \t5.0 \t6.0\n\
+        ITEM: TIME\n0.5\nITEM: TIMESTEP\n200\nITEM: NUMBER OF ATOMS\n1\nITEM: BOX BOUNDS xy xz yz pp pp pp\n\
+        0.0 12.0 2.0\n0.0 10.0 0.0\n0.0 10.0 0.0\nITEM: ATOMS id type xu yu zu vx\n1 2 1.5 2.5 3.5 0.1\n";
+        let frames: Vec<DumpFrame> = DumpReader::new(data).collect::<Result<_, _>>().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].timestep, 100);
+        assert_eq!(frames[0].atoms[1].id, 7);
+        assert_eq!(frames[0].atoms[0].get_name_by_ele_name(), "Cu");
+        assert_eq!(frames[0].atoms[1].atom_location, [4.0, 5.0, 6.0]);
+        let sim_box = frames[0].sim_box.unwrap();
+        assert_eq!(sim_box.origin, [0.0, -1.0, 0.0]);
+        assert_eq!(sim_box.pbc, [true, true, false]);
+        assert_eq!(frames[1].sim_box.unwrap().cell, [[10.0, 0.0, 0.0], [2.0, 10.0, 0.0], [0.0, 0.0, 10.0]]);
+        assert_eq!(frames[1].atoms[0].atom_location, [1.5, 2.5, 3.5]);
+        assert_eq!((frames[0].time, frames[1].time), (None, Some(0.5)));
+
+        assert!(DumpReader::new(&b"ITEM: NUMBER OF ATOMS\n2\nITEM: ATOMS id x y\n"[..]).next().unwrap().is_err());
+    }
+}
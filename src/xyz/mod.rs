@@ -1,2 +1,4 @@
 pub(crate) mod xyz_reader;
 pub(crate) mod particle;
+pub(crate) mod sim_box;
+pub(crate) mod dump_reader;
@@ -5,6 +5,8 @@
 use std::iter::Iterator;
 
 use std::str::FromStr;
+
+use rayon::prelude::*;
 use xyzio::Error;
 
 type Real = f64;
@@ -18,47 +20,6 @@ pub struct Particle {
     pub extra_data: Vec<Real>,
 }
 
-impl Particle {
-    // check 2 atoms' position with an error limit
-    pub(crate) fn is_pos_near_eq(&self, another: &Particle, error_limit: f64) -> bool {
-        return (self.pos.0 - another.pos.0).abs() < error_limit &&
-            (self.pos.1 - another.pos.1).abs() < error_limit &&
-            (self.pos.2 - another.pos.2).abs() < error_limit;
-    }
-
-    // check 2 atoms' position with an error limit and periodic boundary condition.
-    pub(crate) fn is_pos_near_eq_with_pbc(&self, another: &Particle, error_limit: f64, (box_x, box_y, box_z): (f64, f64, f64)) -> bool {
-        return ((self.pos.0 - another.pos.0).abs() < error_limit || ((self.pos.0 - another.pos.0).abs() - box_x).abs() < error_limit)
-            && ((self.pos.1 - another.pos.1).abs() < error_limit || ((self.pos.1 - another.pos.1).abs() - box_y).abs() < error_limit)
-            && ((self.pos.2 - another.pos.2).abs() < error_limit || ((self.pos.2 - another.pos.2).abs() - box_z).abs() < error_limit);
-    }
-
-    fn compare_extra_data(&self, another: &Particle, error_limit: f64) -> bool {
-        if self.extra_data.len() != another.extra_data.len() {
-            return false;
-        }
-        let mut i = 0;
-        for real_value in &self.extra_data {
-            if (real_value - another.extra_data[i]).abs() >= error_limit {
-                return false;
-            }
-            i += 1;
-        }
-        return true;
-    }
-
-    // check 2 atoms' status, including position, and possible velocity, force.
-    pub(crate) fn is_status_near_eq(&self, another: &Particle, error_limit: f64) -> bool {
-        return self.is_pos_near_eq(another, error_limit) && self.compare_extra_data(another, error_limit);
-    }
-
-    // compare atoms status under periodic boundary checking
-    pub(crate) fn is_status_near_eq_with_pbc(&self, another: &Particle, error_limit: f64, box_length: (f64, f64, f64)) -> bool {
-        return self.is_pos_near_eq_with_pbc(another, error_limit, box_length)
-            && self.compare_extra_data(another, error_limit);
-    }
-}
-
 impl FromStr for Particle {
     type Err = Error;
 
@@ -70,9 +31,9 @@ impl FromStr for Particle {
         }
 
         let pos: (Real, Real, Real) = (
-            split[2].parse::<Real>().unwrap(),
-            split[3].parse::<Real>().unwrap(),
-            split[4].parse::<Real>().unwrap(),
+            split[2].parse::<Real>()?,
+            split[3].parse::<Real>()?,
+            split[4].parse::<Real>()?,
         );
 
         let mut particle = Particle {
@@ -92,6 +53,148 @@ impl FromStr for Particle {
     }
 }
 
+/**
+ * a line of atom in xyz file, which is not parsed when reading the file.
+ * It is parsed to [`Particle`] after the columns are known from the comment line (see [`parse_particles`]).
+ */
+pub struct ParticleLine(String);
+
+impl FromStr for ParticleLine {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(ParticleLine(String::from(s)))
+    }
+}
+
+/**
+ * columns of particle in extended xyz file, parsed from `Properties=` in comment line, for example:
+ * `Properties=species:S:1:pos:R:3:velo:R:3:forces:R:3:id:I:1`.
+ * `species` and `pos` are required, and the atom index (start from 1) is used as id if `id` is not specified.
+ */
+struct ExtxyzColumns {
+    // total number of columns
+    columns: usize,
+    species: usize,
+    pos: usize,
+    id: Option<usize>,
+    velo: Option<usize>,
+    forces: Option<usize>,
+    // start column and count of other real properties, they are appended after velocity and force.
+    others: Vec<(usize, usize)>,
+}
+
+impl ExtxyzColumns {
+    // `None` is returned if there is no `Properties` in comment line.
+    fn from_comment(comment: &str) -> Option<Result<ExtxyzColumns, String>> {
+        let pairs = crate::xyz::sim_box::parse_key_values(comment);
+        let (_, properties) = pairs.iter().find(|(k, _)| k.eq_ignore_ascii_case("Properties"))?;
+        Some(ExtxyzColumns::parse(properties))
+    }
+
+    fn parse(properties: &str) -> Result<ExtxyzColumns, String> {
+        let fields: Vec<&str> = properties.split(':').collect();
+        // each property is `name:type:count`
+        let properties_iter = fields.chunks_exact(3);
+        if !properties_iter.remainder().is_empty() {
+            return Err(format!("bad extxyz properties `{}`", properties));
+        }
+        let (mut species, mut pos) = (None, None);
+        let mut columns = ExtxyzColumns { columns: 0, species: 0, pos: 0, id: None, velo: None, forces: None, others: vec![] };
+        for property in properties_iter {
+            let count = property[2].parse::<usize>().map_err(|_| format!("bad count of extxyz property `{}`", property[0]))?;
+            let real3 = property[1] == "R" && count == 3;
+            match property[0] {
+                "species" => species = Some(columns.columns),
+                "pos" if real3 => pos = Some(columns.columns),
+                "id" if count == 1 => columns.id = Some(columns.columns),
+                "velo" if real3 => columns.velo = Some(columns.columns),
+                "forces" if real3 => columns.forces = Some(columns.columns),
+                _ if property[1] == "R" => columns.others.push((columns.columns, count)),
+                _ => {}
+            }
+            columns.columns += count;
+        }
+        match (species, pos) {
+            (Some(species), Some(pos)) => {
+                columns.species = species;
+                columns.pos = pos;
+                Ok(columns)
+            }
+            _ => Err(format!("property `species` or `pos` is not found in extxyz properties `{}`", properties)),
+        }
+    }
+
+    // parse the line of atom with `index` (start from 0) in frame.
+    fn particle(&self, line: &str, index: usize) -> Result<Particle, String> {
+        let split: Vec<&str> = line.split_whitespace().collect();
+        if split.len() != self.columns {
+            return Err(format!("{} columns are required by the extxyz properties, but there are {} columns", self.columns, split.len()));
+        }
+        let real = |i: usize| split[i].parse::<Real>().map_err(|e| format!("bad value `{}`: {}", split[i], e));
+        let id = match self.id {
+            Some(i) => real(i)? as u32,
+            None => index as u32 + 1,
+        };
+        let mut extra_data = vec![];
+        // velocity is filled with zero if only force is saved, thus, the force is still compared as force.
+        match (self.velo, self.forces) {
+            (Some(velo), _) => extra_data.extend([real(velo)?, real(velo + 1)?, real(velo + 2)?].iter()),
+            (None, Some(_)) => extra_data.extend([0.0; 3].iter()),
+            _ => {}
+        }
+        if let Some(forces) = self.forces {
+            extra_data.extend([real(forces)?, real(forces + 1)?, real(forces + 2)?].iter());
+        }
+        for &(start, count) in &self.others {
+            for i in start..start + count {
+                extra_data.push(real(i)?);
+            }
+        }
+        Ok(Particle {
+            id,
+            tp: String::from(split[self.species]),
+            pos: (real(self.pos)?, real(self.pos + 1)?, real(self.pos + 2)?),
+            extra_data,
+        })
+    }
+}
+
+/**
+ * parse lines of atoms in a frame.
+ * If there is `Properties=` in the comment line (extended xyz), the columns are mapped by the properties.
+ * Otherwise, each line is `id type x y z [extra...]`.
+ */
+pub fn parse_particles(comment: &str, lines: &[ParticleLine]) -> Result<Vec<Particle>, String> {
+    match ExtxyzColumns::from_comment(comment) {
+        Some(columns) => {
+            let columns = columns?;
+            lines.par_iter().enumerate()
+                .map(|(i, line)| columns.particle(&line.0, i).map_err(|e| format!("atom {}: {}", i, e)))
+                .collect()
+        }
+        None => lines.par_iter().enumerate()
+            .map(|(i, line)| line.0.parse::<Particle>().map_err(|e| format!("atom {}: {:?}", i, e)))
+            .collect(),
+    }
+}
+
+/**
+ * parse atoms of xyz file for analyses (e.g. `ans`): the columns are mapped by `Properties=` in comment line of
+ * extxyz file, otherwise, the columns of standard xyz file (`species x y z`) are used.
+ * The second value of result is true if atom ids are saved in the file.
+ */
+pub fn parse_xyz_atoms(comment: &str, lines: &[ParticleLine]) -> Result<(Vec<Particle>, bool), String> {
+    let columns = match ExtxyzColumns::from_comment(comment) {
+        Some(columns) => columns?,
+        None => ExtxyzColumns::parse("species:S:1:pos:R:3")?,
+    };
+    let particles = lines.par_iter().enumerate()
+        .map(|(i, line)| columns.particle(&line.0, i).map_err(|e| format!("atom {}: {}", i, e)))
+        .collect::<Result<Vec<Particle>, String>>()?;
+    Ok((particles, columns.id.is_some()))
+}
+
 impl ToString for Particle {
     fn to_string(&self) -> String {
         format!("id: {}, position: ({}, {}, {}), extra_data: {:?})",
@@ -120,4 +223,56 @@ mod tests {
         assert_eq!(3, snapshot.size());
         assert_eq!(3, snapshot.atoms[2].id);
     }
+
+    #[test]
+    fn test_parse_particles() {
+        let lines: Vec<ParticleLine> = ["Fe 1.0 2.0 3.0 0.5 0.0 0.0 7 bcc", "Cu 4.0 3.0 6.0 0.0 0.5 0.0 8 fcc"].iter()
+            .map(|line| line.parse().unwrap()).collect();
+        let comment = "Lattice=\"10 0 0 0 10 0 0 0 10\" Properties=species:S:1:pos:R:3:velo:R:3:id:I:1:structure:S:1 pbc=\"T T T\"";
+        let particles = parse_particles(comment, &lines).unwrap();
+        assert_eq!((particles[1].id, particles[1].tp.as_str()), (8, "Cu"));
+        assert_eq!(particles[1].pos, (4.0, 3.0, 6.0));
+        assert_eq!(particles[0].extra_data, vec![0.5, 0.0, 0.0]);
+
+        // force only, and atom index is used as id.
+        let lines: Vec<ParticleLine> = vec!["Fe 1.0 2.0 3.0 0.1 0.2 0.3".parse().unwrap()];
+        let particles = parse_particles("Properties=species:S:1:pos:R:3:forces:R:3", &lines).unwrap();
+        assert_eq!(particles[0].id, 1);
+        assert_eq!(particles[0].extra_data, vec![0.0, 0.0, 0.0, 0.1, 0.2, 0.3]);
+        assert!(parse_particles("Properties=species:S:1:pos:R:3:id:I:1", &lines).is_err());
+
+        // plain xyz line: id type x y z
+        let lines: Vec<ParticleLine> = vec!["3 Fe 1.0 2.0 3.0".parse().unwrap()];
+        assert_eq!(parse_particles("comment", &lines).unwrap()[0].id, 3);
+    }
+
+    #[test]
+    fn test_parse_xyz_atoms() {
+        // standard xyz: species x y z
+        let lines: Vec<ParticleLine> = vec!["Fe 1.0 2.0 3.0".parse().unwrap()];
+        let (particles, has_id) = parse_xyz_atoms("comment", &lines).unwrap();
+        assert_eq!((particles[0].tp.as_str(), particles[0].pos, has_id), ("Fe", (1.0, 2.0, 3.0), false));
+
+        let lines: Vec<ParticleLine> = vec!["Fe 1.0 2.0 3.0 7".parse().unwrap()];
+        assert!(parse_xyz_atoms("comment", &lines).is_err());
+        let (particles, has_id) = parse_xyz_atoms("Properties=species:S:1:pos:R:3:id:I:1", &lines).unwrap();
+        assert_eq!((particles[0].id, has_id), (7, true));
+    }
+
+    #[test]
+    fn test_frames() {
+        let data: &[u8] = b"\
+            2
+            1st snapshot
+            1 Fe 1.0 2.0 3.0
+            2 Fe 4.0 3.0 6.0
+            2
+            2nd snapshot
+            1 Fe 1.1 2.0 3.0
+            2 Fe 4.0 3.0 bad";
+        let mut frames = Reader::new(data).into_frames::<Particle>();
+        let first = frames.next().unwrap().unwrap();
+        assert_eq!(2, first.atoms[1].id);
+        assert!(frames.next().unwrap().is_err());
+    }
 }
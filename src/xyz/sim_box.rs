@@ -0,0 +1,234 @@
+// Simulation box (orthogonal or triclinic) with periodic boundary conditions.
+
+type Real = f64;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SimBox {
+    // cell vectors a, b and c (each row is a vector).
+    pub cell: [[Real; 3]; 3],
+    // the origin (lower corner) of the box.
+    pub origin: [Real; 3],
+    // periodic boundary in each direction of cell vectors.
+    pub pbc: [bool; 3],
+    // inverse matrix of `cell`, used for converting cartesian coordinate to fractional coordinate.
+    inverse: [[Real; 3]; 3],
+}
+
+impl SimBox {
+    // create a triclinic box by cell vectors (each row is a vector). `None` is returned if the cell is singular.
+    pub fn new(cell: [[Real; 3]; 3], origin: [Real; 3], pbc: [bool; 3]) -> Option<SimBox> {
+        let inverse = inverse_matrix(&cell)?;
+        Some(SimBox { cell, origin, pbc, inverse })
+    }
+
+    // create an orthogonal box by box length, the box is periodic in all directions.
+    pub fn orthogonal(length: (Real, Real, Real), origin: [Real; 3]) -> Option<SimBox> {
+        SimBox::new([[length.0, 0.0, 0.0], [0.0, length.1, 0.0], [0.0, 0.0, length.2]], origin, [true; 3])
+    }
+
+    /**
+     * read box from the comment line of extended xyz file, for example:
+     * `Lattice="10.0 0.0 0.0 0.0 10.0 0.0 0.0 0.0 10.0" Origin="0.0 0.0 0.0" pbc="T T T" Properties=...`
+     * `Origin` and `pbc` are optional, and they are default to zero and periodic.
+     */
+    pub fn from_extxyz_comment(comment: &str) -> Option<SimBox> {
+        let pairs = parse_key_values(comment);
+        let value_of = |key: &str| -> Option<&String> {
+            pairs.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v)
+        };
+
+        let lattice = parse_reals(value_of("Lattice")?)?;
+        if lattice.len() != 9 {
+            return None;
+        }
+        let cell = [[lattice[0], lattice[1], lattice[2]],
+            [lattice[3], lattice[4], lattice[5]],
+            [lattice[6], lattice[7], lattice[8]]];
+
+        let mut origin = [0.0; 3];
+        if let Some(value) = value_of("Origin") {
+            let values = parse_reals(value)?;
+            if values.len() != 3 {
+                return None;
+            }
+            origin.copy_from_slice(&values);
+        }
+
+        let mut pbc = [true; 3];
+        if let Some(value) = value_of("pbc") {
+            let flags: Vec<&str> = value.split_whitespace().collect();
+            if flags.len() != 3 {
+                return None;
+            }
+            for d in 0..3 {
+                pbc[d] = matches!(flags[d], "T" | "t" | "True" | "true" | "1");
+            }
+        }
+        SimBox::new(cell, origin, pbc)
+    }
+
+    /**
+     * minimum image of displacement vector `d` (cartesian) under periodic boundary condition:
+     * the displacement is shifted by box vectors in periodic directions to make its fractional coordinate in [-0.5, 0.5].
+     */
+    pub fn minimum_image(&self, d: [Real; 3]) -> [Real; 3] {
+        let mut f = mul_vec_matrix(&d, &self.inverse);
+        for (f, &pbc) in f.iter_mut().zip(self.pbc.iter()) {
+            if pbc {
+                *f -= f.round();
+            }
+        }
+        mul_vec_matrix(&f, &self.cell)
+    }
+
+    pub fn volume(&self) -> Real {
+        determinant(&self.cell).abs()
+    }
+
+    // fractional coordinate of position `r` (cartesian) relative to the box origin.
+    pub fn fractional(&self, r: [Real; 3]) -> [Real; 3] {
+        mul_vec_matrix(&[r[0] - self.origin[0], r[1] - self.origin[1], r[2] - self.origin[2]], &self.inverse)
+    }
+
+    // cartesian vector of fractional vector `f`.
+    pub fn cartesian(&self, f: [Real; 3]) -> [Real; 3] {
+        mul_vec_matrix(&f, &self.cell)
+    }
+
+    // distances between opposite faces of the box, in each direction of cell vectors.
+    pub fn heights(&self) -> [Real; 3] {
+        let volume = self.volume();
+        let mut heights = [0.0; 3];
+        for (d, h) in heights.iter_mut().enumerate() {
+            let (b, c) = (self.cell[(d + 1) % 3], self.cell[(d + 2) % 3]);
+            let cross = [b[1] * c[2] - b[2] * c[1], b[2] * c[0] - b[0] * c[2], b[0] * c[1] - b[1] * c[0]];
+            *h = volume / (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt();
+        }
+        heights
+    }
+}
+
+// split `key=value key2="value with spaces"` into key and value pairs.
+pub(crate) fn parse_key_values(line: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    loop {
+        // skip white spaces
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c.is_whitespace() {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    value.push(c);
+                }
+            } else {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+            }
+        }
+        pairs.push((key, value));
+    }
+    pairs
+}
+
+fn parse_reals(value: &str) -> Option<Vec<Real>> {
+    value.split_whitespace().map(|v| v.parse::<Real>().ok()).collect()
+}
+
+fn determinant(m: &[[Real; 3]; 3]) -> Real {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+fn inverse_matrix(m: &[[Real; 3]; 3]) -> Option<[[Real; 3]; 3]> {
+    let det = determinant(m);
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+    let mut inv = [[0.0; 3]; 3];
+    for (i, row) in inv.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            // cofactor of element (j, i), divided by determinant.
+            let (r1, r2) = ((j + 1) % 3, (j + 2) % 3);
+            let (c1, c2) = ((i + 1) % 3, (i + 2) % 3);
+            *value = (m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]) / det;
+        }
+    }
+    Some(inv)
+}
+
+// row vector `v` multiplied by matrix `m`.
+fn mul_vec_matrix(v: &[Real; 3], m: &[[Real; 3]; 3]) -> [Real; 3] {
+    let mut r = [0.0; 3];
+    for j in 0..3 {
+        r[j] = v[0] * m[0][j] + v[1] * m[1][j] + v[2] * m[2][j];
+    }
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_vec_eq(a: [Real; 3], b: [Real; 3]) {
+        for d in 0..3 {
+            assert!((a[d] - b[d]).abs() < 1e-9, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_orthogonal_minimum_image() {
+        let sim_box = SimBox::orthogonal((10.0, 20.0, 30.0), [0.0; 3]).unwrap();
+        assert_vec_eq(sim_box.minimum_image([9.9, -19.0, 1.0]), [-0.1, 1.0, 1.0]);
+        // off by more than one image.
+        assert_vec_eq(sim_box.minimum_image([25.5, 0.0, -61.0]), [-4.5, 0.0, -1.0]);
+        assert!((sim_box.volume() - 6000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_triclinic_box() {
+        let cell = [[10.0, 0.0, 0.0], [5.0, 10.0, 0.0], [0.0, 0.0, 10.0]];
+        let sim_box = SimBox::new(cell, [1.0, 1.0, 1.0], [true, true, false]).unwrap();
+        // shifted by the b vector, and z is not periodic.
+        assert_vec_eq(sim_box.minimum_image([5.1, 9.9, 12.0]), [0.1, -0.1, 12.0]);
+        assert_vec_eq(sim_box.fractional([8.5, 6.0, 3.0]), [0.5, 0.5, 0.2]);
+        assert_vec_eq(sim_box.cartesian([0.5, 0.5, 0.2]), [7.5, 5.0, 2.0]);
+        assert_vec_eq(sim_box.heights(), [10.0 * 10.0 / 125.0_f64.sqrt(), 10.0, 10.0]);
+        assert!(SimBox::new([[1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 0.0, 1.0]], [0.0; 3], [true; 3]).is_none());
+    }
+
+    #[test]
+    fn test_from_extxyz_comment() {
+        let comment = "Lattice=\"10.0 0.0 0.0 0.0 20.0 0.0 0.0 0.0 30.0\" Origin=\"-1 0 0.5\" \
+        Properties=species:S:1:pos:R:3:id:I:1 pbc=\"T T F\"";
+        let sim_box = SimBox::from_extxyz_comment(comment).unwrap();
+        assert_eq!(sim_box.cell, [[10.0, 0.0, 0.0], [0.0, 20.0, 0.0], [0.0, 0.0, 30.0]]);
+        assert_vec_eq(sim_box.origin, [-1.0, 0.0, 0.5]);
+        assert_eq!(sim_box.pbc, [true, true, false]);
+
+        assert!(SimBox::from_extxyz_comment("comment").is_none());
+        assert!(SimBox::from_extxyz_comment("Lattice=\"1 0 0 0 1 0\"").is_none());
+    }
+}
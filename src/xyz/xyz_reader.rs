@@ -3,13 +3,14 @@
  * but add `rayon` parallel support for reading file
  */
 
+use std::fmt;
 use std::io;
 use std::io::prelude::BufRead;
 use std::iter::Iterator;
+use std::marker::PhantomData;
 
 use rayon::prelude::*;
 use std::str::FromStr;
-use xyzio::Atom;
 use std::fmt::Debug;
 
 pub struct Snapshot<A> where A: FromStr {
@@ -25,75 +26,147 @@ impl<A: FromStr> Snapshot<A> {
 
 pub struct Reader<R> {
     reader: io::BufReader<R>,
+    // count of lines have been read, used for locating the line in error reporting.
+    line_no: usize,
 }
 
-macro_rules! parse_line {
-    ($reader:ident) => {{
-        let mut buffer = String::new();
-        $reader.read_line(&mut buffer)?;
-        buffer
-    }};
-    ($reader:ident, $t:ty) => {{
-        let mut buffer = String::new();
-        $reader.read_line(&mut buffer)?;
-        buffer.trim().parse::<$t>()?
-    }}
+/**
+ * max lines (atoms) of a chunk.
+ * Lines in a chunk are read into memory and then parsed in parallel.
+ */
+const CHUNK_LINES: usize = 1 << 16;
+
+#[derive(Debug)]
+pub enum ReadError {
+    // io error or error in header (the first 2 lines of a frame)
+    Xyz(xyzio::Error),
+    // error in parsing a line of atom, with line number (start from 1) and message.
+    Line(usize, String),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::Xyz(e) => write!(f, "{:?}", e),
+            ReadError::Line(line, msg) => write!(f, "line {}: {}", line, msg),
+        }
+    }
+}
+
+impl From<xyzio::Error> for ReadError {
+    fn from(err: xyzio::Error) -> Self {
+        ReadError::Xyz(err)
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        ReadError::Xyz(xyzio::Error::IO(err))
+    }
 }
 
 impl<R: io::Read> Reader<R> {
     /** we suggest use [`std::fs::File`] as [`inner`] R.
-     * Because all data will be read into memory from inner first and then perform parsing.
-     * For example, if we use memory bytes array as `inner`,
-     * then there are two copies in memory (one is in inner and another one is saved before parsing),
-     * which will cause memory wasting.
+     * Atoms lines are read into memory chunk by chunk (at most [`CHUNK_LINES`] lines)
+     * and then parsed in parallel, thus only the parsed frame is kept in memory.
      */
     pub fn new(inner: R) -> Self {
         Reader {
-            reader: io::BufReader::new(inner)
+            reader: io::BufReader::new(inner),
+            line_no: 0,
         }
     }
 
-    // read and parse data in xyz file in parallel.
-    pub fn read_snapshot<A>(&mut self) -> Result<Snapshot<A>, xyzio::Error> where A: FromStr + Send + Sync, <A as FromStr>::Err: Debug {
-        let reader = &mut self.reader;
-
-        let num_atoms = parse_line!(reader, usize);
-        let comment = parse_line!(reader);
+    // turn the reader into an iterator of frames.
+    pub fn into_frames<A>(self) -> ItlReader<R, A> where A: FromStr {
+        ItlReader { reader: self, done: false, _atom: PhantomData }
+    }
 
-        // read all lines into memory first.
-        // all lines in xyz file (except for file header).
-        let mut atoms_lines: Vec<String> = Vec::new();
-        for _ in 0..num_atoms {
-            let mut buffer = String::new();
-            reader.read_line(&mut buffer).unwrap();
-            atoms_lines.push(buffer);
+    // read and parse a frame in xyz file in parallel.
+    pub fn read_snapshot<A>(&mut self) -> Result<Snapshot<A>, ReadError> where A: FromStr + Send + Sync, <A as FromStr>::Err: Debug {
+        match self.read_frame()? {
+            Some(snapshot) => Ok(snapshot),
+            None => Err(ReadError::Xyz(xyzio::Error::IllegalState(String::from("no frame left in xyz file")))),
         }
+    }
 
-        // parsing data in parallel.
-        let atoms: Vec<_> = (0..num_atoms).into_par_iter().map(|i| {
-            atoms_lines[i].trim().parse::<A>().unwrap()
-        }).collect();
+    // read next frame, `None` is returned if it reaches the end of file.
+    pub fn read_frame<A>(&mut self) -> Result<Option<Snapshot<A>>, ReadError> where A: FromStr + Send + Sync, <A as FromStr>::Err: Debug {
+        // skip blank lines before the header, and stop at end of file.
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            if self.reader.read_line(&mut buffer)? == 0 {
+                return Ok(None);
+            }
+            self.line_no += 1;
+            if !buffer.trim().is_empty() {
+                break;
+            }
+        }
+        let num_atoms = match buffer.trim().parse::<usize>() {
+            Ok(n) => n,
+            Err(e) => return Err(ReadError::Line(self.line_no, format!("bad atoms number: {}", e))),
+        };
+        let mut comment = String::new();
+        self.reader.read_line(&mut comment)?;
+        self.line_no += 1;
+
+        let mut atoms: Vec<A> = Vec::with_capacity(num_atoms);
+        let mut atoms_lines: Vec<String> = Vec::with_capacity(std::cmp::min(num_atoms, CHUNK_LINES));
+        while atoms.len() < num_atoms {
+            // read lines of a chunk into memory.
+            atoms_lines.clear();
+            let chunk_size = std::cmp::min(num_atoms - atoms.len(), CHUNK_LINES);
+            let first_line_no = self.line_no + 1;
+            for _ in 0..chunk_size {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line)? == 0 {
+                    return Err(ReadError::Line(self.line_no + 1, String::from("unexpected end of file")));
+                }
+                self.line_no += 1;
+                atoms_lines.push(line);
+            }
+
+            // parsing data in parallel.
+            let chunk: Result<Vec<A>, ReadError> = atoms_lines.par_iter().enumerate().map(|(i, line)| {
+                line.trim().parse::<A>().map_err(|e| ReadError::Line(first_line_no + i, format!("{:?}", e)))
+            }).collect();
+            atoms.extend(chunk?);
+        }
 
-        Ok(Snapshot { comment, atoms })
+        Ok(Some(Snapshot { comment, atoms }))
     }
 }
 
-pub struct ItlReader<R> {
-    reader: Reader<R>
+// iterator of frames in xyz file, the item is the parsing result of each frame.
+pub struct ItlReader<R, A> {
+    reader: Reader<R>,
+    // set after the end of file or the first error, the rest of a broken frame can not be read as frames.
+    done: bool,
+    _atom: PhantomData<A>,
 }
 
-// next can only used for `Atom` type parsing.
-impl<R: io::Read> Iterator for ItlReader<R> {
-    type Item = Snapshot<Atom>;
+impl<R: io::Read, A> Iterator for ItlReader<R, A> where A: FromStr + Send + Sync, <A as FromStr>::Err: Debug {
+    type Item = Result<Snapshot<A>, ReadError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.reader.read_snapshot().ok()
+        if self.done {
+            return None;
+        }
+        let frame = self.reader.read_frame().transpose();
+        match frame {
+            Some(Ok(_)) => {}
+            _ => self.done = true,
+        }
+        frame
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use xyzio::Atom;
 
     #[test]
     fn test_reader() {
@@ -125,9 +198,44 @@ mod tests {
             O 4.2 3.0 5.9
             H 5.0 1.6 4.0";
         let reader = Reader::new(data);
-        let mut itl_reader = ItlReader { reader };
+        let mut itl_reader = reader.into_frames::<Atom>();
         assert!(itl_reader.next().is_some());
         assert!(itl_reader.next().is_some());
         assert!(itl_reader.next().is_none());
     }
+
+    #[test]
+    fn test_line_error() {
+        let data: &[u8] = b"\
+            3
+            comment
+            C 1.0 2.0 3.0
+            O 4.0 x 6.0
+            H 5.0 1.5 4.0";
+        let mut reader = Reader::new(data);
+        match reader.read_snapshot::<Atom>() {
+            Err(ReadError::Line(line, _)) => assert_eq!(line, 4),
+            _ => panic!("parsing error is expected"),
+        }
+
+        // missing atom lines
+        let data: &[u8] = b"\
+            3
+            comment
+            C 1.0 2.0 3.0";
+        let mut reader = Reader::new(data);
+        assert!(reader.read_snapshot::<Atom>().is_err());
+
+        // the iterator stops at the first error, instead of reading the rest of the broken frame.
+        let data: &[u8] = b"\
+            3
+            comment
+            C 1.0 x 3.0
+            1
+            O 4.0 3.0 6.0
+            H 5.0 1.5 4.0";
+        let mut itl_reader = Reader::new(data).into_frames::<Atom>();
+        assert!(matches!(itl_reader.next(), Some(Err(ReadError::Line(3, _)))));
+        assert!(itl_reader.next().is_none());
+    }
 }